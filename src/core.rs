@@ -1,3 +1,21 @@
+pub mod attrs;
+pub mod compat;
 pub mod consts;
+pub mod coverage;
 pub mod db;
+pub mod diff;
+pub mod docs;
+pub mod fixtures;
+pub mod graph;
+pub mod group;
+pub mod jsonpath;
 mod op;
+pub mod pydantic;
+pub mod roundtrip;
+pub mod sample;
+pub mod schema;
+pub mod simplify;
+pub mod snapshot;
+pub mod stats;
+pub mod template;
+pub mod typescript;
@@ -0,0 +1,419 @@
+use serde::{Deserialize, Serialize};
+use syn::{Attribute, Lit, Meta, NestedMeta};
+
+/// A `#[serde(...)]` value that can differ for serialization and deserialization,
+/// like `rename(serialize = "a", deserialize = "b")`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pair {
+    pub ser: Option<String>,
+    pub de: Option<String>,
+}
+
+impl Pair {
+    fn set(&mut self, meta: &Meta) {
+        match meta {
+            Meta::NameValue(nv) => {
+                if let Lit::Str(s) = &nv.lit {
+                    self.ser = Some(s.value());
+                    self.de = Some(s.value());
+                }
+            }
+            Meta::List(list) => {
+                for (key, value) in name_values(&list.nested) {
+                    match key.as_str() {
+                        "serialize" => self.ser = Some(value),
+                        "deserialize" => self.de = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+            Meta::Path(_) => {}
+        }
+    }
+}
+
+/// Container (struct or enum) level serde attributes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContainerAttrs {
+    pub rename: Pair,
+    pub rename_all: Pair,
+    pub tag: Option<String>,
+    pub content: Option<String>,
+    pub untagged: bool,
+    pub transparent: bool,
+    pub deny_unknown_fields: bool,
+    pub default: bool,
+    /// `from` or `try_from` proxy type
+    pub from: Option<String>,
+    /// `into` proxy type
+    pub into: Option<String>,
+}
+
+/// Field level serde attributes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldAttrs {
+    pub rename: Pair,
+    pub aliases: Vec<String>,
+    pub default: bool,
+    pub skip_serializing: bool,
+    pub skip_deserializing: bool,
+    pub skip_serializing_if: Option<String>,
+    pub flatten: bool,
+    /// `with` module or `serialize_with`/`deserialize_with` functions
+    pub with: Pair,
+}
+
+/// Enum variant level serde attributes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VariantAttrs {
+    pub rename: Pair,
+    pub aliases: Vec<String>,
+    /// `#[serde(other)]`: unknown variants are deserialized as this one
+    pub other: bool,
+    pub skip_serializing: bool,
+    pub skip_deserializing: bool,
+    /// `with` module or `serialize_with`/`deserialize_with` functions
+    pub with: Pair,
+}
+
+/// The enum representation in JSON
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tagging {
+    /// `{"Variant": {...}}`
+    External,
+    /// `{"tag": "Variant", ...}`
+    Internal(String),
+    /// `{"tag": "Variant", "content": {...}}`
+    Adjacent(String, String),
+    /// `{...}`
+    Untagged,
+}
+
+impl std::fmt::Display for Tagging {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tagging::External => write!(f, "externally tagged"),
+            Tagging::Internal(tag) => write!(f, "internally tagged (tag = {:?})", tag),
+            Tagging::Adjacent(tag, content) => write!(
+                f,
+                "adjacently tagged (tag = {:?}, content = {:?})",
+                tag, content
+            ),
+            Tagging::Untagged => write!(f, "untagged"),
+        }
+    }
+}
+
+/// Items of all `#[serde(...)]` attributes
+fn serde_metas(attrs: &[Attribute]) -> Vec<Meta> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident("serde"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested.into_iter().collect::<Vec<NestedMeta>>()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(m) => Some(m),
+            NestedMeta::Lit(_) => None,
+        })
+        .collect()
+}
+
+/// `key = "value"` pairs of a nested list
+fn name_values(
+    nested: &syn::punctuated::Punctuated<NestedMeta, syn::token::Comma>,
+) -> Vec<(String, String)> {
+    nested
+        .iter()
+        .filter_map(|n| match n {
+            NestedMeta::Meta(Meta::NameValue(nv)) => match &nv.lit {
+                Lit::Str(s) => Some((meta_name(&Meta::NameValue(nv.clone())), s.value())),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+fn meta_name(meta: &Meta) -> String {
+    meta.path()
+        .get_ident()
+        .map(|i| i.to_string())
+        .unwrap_or_default()
+}
+
+fn meta_str(meta: &Meta) -> Option<String> {
+    match meta {
+        Meta::NameValue(nv) => match &nv.lit {
+            Lit::Str(s) => Some(s.value()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl ContainerAttrs {
+    pub fn new(attrs: &[Attribute]) -> Self {
+        let mut result = Self::default();
+        for meta in serde_metas(attrs) {
+            match meta_name(&meta).as_str() {
+                "rename" => result.rename.set(&meta),
+                "rename_all" => result.rename_all.set(&meta),
+                "tag" => result.tag = meta_str(&meta),
+                "content" => result.content = meta_str(&meta),
+                "untagged" => result.untagged = true,
+                "transparent" => result.transparent = true,
+                "deny_unknown_fields" => result.deny_unknown_fields = true,
+                "default" => result.default = true,
+                "from" | "try_from" => result.from = meta_str(&meta),
+                "into" => result.into = meta_str(&meta),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Enum representation
+    pub fn tagging(&self) -> Tagging {
+        if self.untagged {
+            Tagging::Untagged
+        } else {
+            match (&self.tag, &self.content) {
+                (Some(tag), Some(content)) => Tagging::Adjacent(tag.clone(), content.clone()),
+                (Some(tag), None) => Tagging::Internal(tag.clone()),
+                _ => Tagging::External,
+            }
+        }
+    }
+}
+
+impl FieldAttrs {
+    pub fn new(attrs: &[Attribute]) -> Self {
+        let mut result = Self::default();
+        for meta in serde_metas(attrs) {
+            match meta_name(&meta).as_str() {
+                "rename" => result.rename.set(&meta),
+                "alias" => result.aliases.extend(meta_str(&meta)),
+                "default" => result.default = true,
+                "skip" => {
+                    result.skip_serializing = true;
+                    result.skip_deserializing = true;
+                }
+                "skip_serializing" => result.skip_serializing = true,
+                "skip_deserializing" => result.skip_deserializing = true,
+                "skip_serializing_if" => result.skip_serializing_if = meta_str(&meta),
+                "flatten" => result.flatten = true,
+                "with" => {
+                    result.with.ser = meta_str(&meta);
+                    result.with.de = meta_str(&meta);
+                }
+                "serialize_with" => result.with.ser = meta_str(&meta),
+                "deserialize_with" => result.with.de = meta_str(&meta),
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+impl VariantAttrs {
+    pub fn new(attrs: &[Attribute]) -> Self {
+        let mut result = Self::default();
+        for meta in serde_metas(attrs) {
+            match meta_name(&meta).as_str() {
+                "rename" => result.rename.set(&meta),
+                "alias" => result.aliases.extend(meta_str(&meta)),
+                "other" => result.other = true,
+                "skip" => {
+                    result.skip_serializing = true;
+                    result.skip_deserializing = true;
+                }
+                "skip_serializing" => result.skip_serializing = true,
+                "skip_deserializing" => result.skip_deserializing = true,
+                "with" => {
+                    result.with.ser = meta_str(&meta);
+                    result.with.de = meta_str(&meta);
+                }
+                "serialize_with" => result.with.ser = meta_str(&meta),
+                "deserialize_with" => result.with.de = meta_str(&meta),
+                _ => {}
+            }
+        }
+        result
+    }
+}
+
+/// The `///` comments (`#[doc = "..."]` attributes) of an item, field or variant
+pub fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| a.parse_meta().ok())
+        .filter_map(|meta| meta_str(&meta))
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(&line)
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>();
+    let doc = lines.join("\n").trim().to_string();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
+/// Items of the `#[prost(...)]` attribute of a prost message field, like `uint64, tag = "1"`
+pub fn prost(attrs: &[Attribute]) -> Option<String> {
+    let items = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("prost"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested.into_iter().collect::<Vec<NestedMeta>>()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) => match &nv.lit {
+                Lit::Str(s) => Some(format!(
+                    "{} = {:?}",
+                    meta_name(&Meta::NameValue(nv.clone())),
+                    s.value()
+                )),
+                _ => None,
+            },
+            NestedMeta::Meta(m) => Some(meta_name(&m)),
+            NestedMeta::Lit(_) => None,
+        })
+        .collect::<Vec<String>>();
+    if items.is_empty() {
+        None
+    } else {
+        Some(items.join(", "))
+    }
+}
+
+/// Apply a `rename_all` rule to a snake_case field name, the way serde does it.
+pub fn rename_field(name: &str, rule: &str) -> String {
+    match rule {
+        "lowercase" | "snake_case" => name.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => name.to_ascii_uppercase(),
+        "kebab-case" => name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => name.to_ascii_uppercase().replace('_', "-"),
+        "PascalCase" | "camelCase" => {
+            let mut result = String::new();
+            let mut capitalize = rule == "PascalCase";
+            for c in name.chars() {
+                if c == '_' {
+                    capitalize = true;
+                } else if capitalize {
+                    result.push(c.to_ascii_uppercase());
+                    capitalize = false;
+                } else {
+                    result.push(c);
+                }
+            }
+            result
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Apply a `rename_all` rule to a PascalCase variant name, the way serde does it.
+pub fn rename_variant(name: &str, rule: &str) -> String {
+    let snake = || {
+        let mut result = String::new();
+        for (i, c) in name.char_indices() {
+            if c.is_uppercase() && i != 0 {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        }
+        result
+    };
+    match rule {
+        "lowercase" => name.to_ascii_lowercase(),
+        "UPPERCASE" => name.to_ascii_uppercase(),
+        "camelCase" => {
+            let mut chars = name.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_lowercase().collect::<String>() + chars.as_str()
+            })
+        }
+        "snake_case" => snake(),
+        "SCREAMING_SNAKE_CASE" => snake().to_ascii_uppercase(),
+        "kebab-case" => snake().replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => snake().to_ascii_uppercase().replace('_', "-"),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &[&str] = &[
+        "lowercase",
+        "UPPERCASE",
+        "PascalCase",
+        "camelCase",
+        "snake_case",
+        "SCREAMING_SNAKE_CASE",
+        "kebab-case",
+        "SCREAMING-KEBAB-CASE",
+    ];
+
+    #[test]
+    fn rename_field_rules() {
+        let expected = [
+            "last_block_id",
+            "LAST_BLOCK_ID",
+            "LastBlockId",
+            "lastBlockId",
+            "last_block_id",
+            "LAST_BLOCK_ID",
+            "last-block-id",
+            "LAST-BLOCK-ID",
+        ];
+        for (rule, expected) in RULES.iter().zip(expected.iter()) {
+            assert_eq!(rename_field("last_block_id", rule), *expected, "{}", rule);
+        }
+        assert_eq!(rename_field("last_block_id", "unknown"), "last_block_id");
+    }
+
+    #[test]
+    fn rename_variant_rules() {
+        let expected = [
+            "newblockevents",
+            "NEWBLOCKEVENTS",
+            "NewBlockEvents",
+            "newBlockEvents",
+            "new_block_events",
+            "NEW_BLOCK_EVENTS",
+            "new-block-events",
+            "NEW-BLOCK-EVENTS",
+        ];
+        for (rule, expected) in RULES.iter().zip(expected.iter()) {
+            assert_eq!(
+                rename_variant("NewBlockEvents", rule),
+                *expected,
+                "{}",
+                rule
+            );
+        }
+        assert_eq!(
+            rename_variant("NewBlockEvents", "unknown"),
+            "NewBlockEvents"
+        );
+    }
+
+    #[test]
+    fn rename_variant_non_ascii() {
+        assert_eq!(rename_variant("Été", "camelCase"), "été");
+        assert_eq!(rename_variant("", "camelCase"), "");
+    }
+}
@@ -0,0 +1,775 @@
+use crate::core::db::{Collection, Color, DataType, Entry, Field};
+use crate::core::diff;
+use crate::core::op;
+use crate::core::schema::POINTERS;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use syn::Type;
+
+/// Effect of a change in one direction.
+/// Serialization: can readers of the old JSON still read the JSON written by the new version?
+/// Deserialization: can the new version still read the JSON written by the old version?
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Impact {
+    Breaking,
+    NonBreaking,
+}
+
+impl fmt::Display for Impact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match &self {
+            Impact::Breaking => "breaking",
+            Impact::NonBreaking => "non-breaking",
+        })
+    }
+}
+
+use Impact::{Breaking, NonBreaking};
+
+fn breaking_if(condition: bool) -> Impact {
+    if condition {
+        Breaking
+    } else {
+        NonBreaking
+    }
+}
+
+/// A wire format change of a type
+#[derive(Debug)]
+pub struct Finding {
+    /// Entry id (the new id for renamed entries)
+    pub id: String,
+    pub description: String,
+    pub ser: Impact,
+    pub de: Impact,
+}
+
+/// How a type is (de)serialized in one direction
+#[derive(Debug, PartialEq)]
+enum Representation {
+    None,
+    /// `impl Serialize`/`impl Deserialize`, the shape is unknown
+    Custom,
+    /// `#[serde(try_from/from/into = "...")]` with the (path-stripped) proxy type
+    Proxy(String),
+    /// `#[serde(transparent)]` or derived
+    Derive,
+}
+
+impl fmt::Display for Representation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Representation::None => write!(f, "not implemented"),
+            Representation::Custom => write!(f, "custom implementation"),
+            Representation::Proxy(t) => write!(f, "proxy type {}", t),
+            Representation::Derive => write!(f, "derived"),
+        }
+    }
+}
+
+fn representation(entry: &Entry, serialize: bool) -> Representation {
+    let (derive, custom, proxy) = if serialize {
+        (entry.serialize, entry.serializer, &entry.serde.into)
+    } else {
+        (entry.deserialize, entry.deserializer, &entry.serde.from)
+    };
+    if custom {
+        Representation::Custom
+    } else if !derive {
+        Representation::None
+    } else if let Some(proxy) = proxy {
+        Representation::Proxy(op::strip_paths(proxy))
+    } else {
+        Representation::Derive
+    }
+}
+
+/// Wire compatibility report of two collections
+#[derive(Debug, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    /// Old type name -> new type name of the renamed types
+    renames: BTreeMap<String, String>,
+}
+
+impl Report {
+    /// Compare the serde models of the JSON-serializable public entries.
+    pub fn new(old: &Collection, new: &Collection) -> Self {
+        let mut report = Report::default();
+        let (added, removed, renamed) = diff::match_entries(old, new);
+        let serializable = |e: &Entry| e.get_color() != Color::White;
+        let name = |id: &str| id.rsplit("::").next().unwrap_or(id).to_string();
+        report.renames = renamed
+            .iter()
+            .map(|(old_id, new_id)| (name(old_id), name(new_id)))
+            .filter(|(old_name, new_name)| old_name != new_name)
+            .collect();
+
+        for id in &removed {
+            if serializable(old.get(id).unwrap()) {
+                report.push(id, "type removed".to_string(), Breaking, Breaking);
+            }
+        }
+        for id in &added {
+            if serializable(new.get(id).unwrap()) {
+                report.push(id, "type added".to_string(), NonBreaking, NonBreaking);
+            }
+        }
+        let common = old
+            .entries()
+            .filter(|(id, e)| e.public && new.get(id).is_some_and(|n| n.public))
+            .map(|(id, _)| (id.clone(), id.clone()))
+            .chain(renamed);
+        for (old_id, new_id) in common {
+            let (o, n) = (old.get(&old_id).unwrap(), new.get(&new_id).unwrap());
+            if serializable(o) || serializable(n) {
+                report.compare_entries(&new_id, o, n);
+            }
+        }
+        report
+    }
+
+    fn push(&mut self, id: &str, description: String, ser: Impact, de: Impact) {
+        self.findings.push(Finding {
+            id: id.to_string(),
+            description,
+            ser,
+            de,
+        });
+    }
+
+    fn compare_entries(&mut self, id: &str, old: &Entry, new: &Entry) {
+        let (old_ser, new_ser) = (representation(old, true), representation(new, true));
+        let (old_de, new_de) = (representation(old, false), representation(new, false));
+        if old_ser != new_ser {
+            self.push(
+                id,
+                format!("Serialize: {} -> {}", old_ser, new_ser),
+                breaking_if(old_ser != Representation::None),
+                NonBreaking,
+            );
+        }
+        if old_de != new_de {
+            self.push(
+                id,
+                format!("Deserialize: {} -> {}", old_de, new_de),
+                NonBreaking,
+                breaking_if(old_de != Representation::None),
+            );
+        }
+        // The shape is only known for derived implementations
+        let ser = old_ser == Representation::Derive && new_ser == Representation::Derive;
+        let de = old_de == Representation::Derive && new_de == Representation::Derive;
+        if !ser && !de {
+            return;
+        }
+        let only = |impact_ser: Impact, impact_de: Impact| {
+            (
+                if ser { impact_ser } else { NonBreaking },
+                if de { impact_de } else { NonBreaking },
+            )
+        };
+
+        let (o, n) = (&old.serde, &new.serde);
+        if o.transparent != n.transparent {
+            let (s, d) = only(Breaking, Breaking);
+            self.push(
+                id,
+                format!("transparent: {} -> {}", o.transparent, n.transparent),
+                s,
+                d,
+            );
+        }
+        if let DataType::Enum = new.r#type {
+            if o.tagging() != n.tagging() {
+                let (s, d) = only(Breaking, Breaking);
+                self.push(
+                    id,
+                    format!("enum representation: {} -> {}", o.tagging(), n.tagging()),
+                    s,
+                    d,
+                );
+            }
+        }
+        if o.deny_unknown_fields != n.deny_unknown_fields {
+            let (s, d) = only(NonBreaking, breaking_if(n.deny_unknown_fields));
+            self.push(
+                id,
+                format!(
+                    "deny_unknown_fields: {} -> {}",
+                    o.deny_unknown_fields, n.deny_unknown_fields
+                ),
+                s,
+                d,
+            );
+        }
+        if let DataType::Enum = new.r#type {
+            self.compare_variants(id, old, new, &only);
+        }
+
+        // Fields of added or removed variants are covered by the variant
+        let has_variant = |entry: &Entry, field: &Field| {
+            field
+                .variant()
+                .is_none_or(|v| entry.members.iter().any(|f| f.variant() == Some(v)))
+        };
+        for old_field in &old.members {
+            if !has_variant(new, old_field) {
+                continue;
+            }
+            match new.members.iter().find(|f| f.name == old_field.name) {
+                Some(new_field) => {
+                    self.compare_fields(id, old, new, old_field, new_field, &only);
+                }
+                None if old_field.ty.is_empty() => {}
+                None if is_positional(old_field) => {
+                    let (s, d) = only(Breaking, Breaking);
+                    self.push(id, format!("field {} removed", old_field.name), s, d);
+                }
+                None => {
+                    let required = !old_field.is_optional(&old.serde);
+                    let (s, d) = only(
+                        breaking_if(old_field.json_key(o, true).is_some() && required),
+                        breaking_if(n.deny_unknown_fields),
+                    );
+                    self.push(id, format!("field {} removed", old_field.name), s, d);
+                }
+            }
+        }
+        for new_field in &new.members {
+            if new_field.ty.is_empty()
+                || !has_variant(old, new_field)
+                || old.members.iter().any(|f| f.name == new_field.name)
+            {
+                continue;
+            }
+            let (s, d) = if is_positional(new_field) {
+                only(Breaking, Breaking)
+            } else {
+                only(
+                    breaking_if(new_field.json_key(n, true).is_some() && o.deny_unknown_fields),
+                    breaking_if(!new_field.is_optional(n)),
+                )
+            };
+            self.push(
+                id,
+                format!("field {}: {} added", new_field.name, new_field.ty),
+                s,
+                d,
+            );
+        }
+    }
+
+    fn compare_variants(
+        &mut self,
+        id: &str,
+        old: &Entry,
+        new: &Entry,
+        only: &dyn Fn(Impact, Impact) -> (Impact, Impact),
+    ) {
+        let (o, n) = (&old.serde, &new.serde);
+        let old_variants = old.get_variants();
+        let new_variants = new.get_variants();
+        for old_variant in &old_variants {
+            let name = &old_variant.name;
+            let new_variant = match new_variants.iter().find(|v| &v.name == name) {
+                None => {
+                    let (s, d) = only(NonBreaking, Breaking);
+                    self.push(id, format!("variant {} removed", name), s, d);
+                    continue;
+                }
+                Some(v) => v,
+            };
+            if old_variant.kind != new_variant.kind {
+                let (s, d) = only(Breaking, Breaking);
+                self.push(
+                    id,
+                    format!(
+                        "variant {}: {:?} -> {:?}",
+                        name, old_variant.kind, new_variant.kind
+                    ),
+                    s,
+                    d,
+                );
+            }
+            match (
+                old_variant.json_name(o, true),
+                new_variant.json_name(n, true),
+            ) {
+                (Some(a), Some(b)) if a != b => {
+                    let (s, d) = only(Breaking, NonBreaking);
+                    self.push(
+                        id,
+                        format!("variant {}: serialized name {:?} -> {:?}", name, a, b),
+                        s,
+                        d,
+                    );
+                }
+                (Some(_), None) => {
+                    let (s, d) = only(NonBreaking, NonBreaking);
+                    self.push(id, format!("variant {}: no longer serialized", name), s, d);
+                }
+                (None, Some(_)) => {
+                    let (s, d) = only(Breaking, NonBreaking);
+                    self.push(id, format!("variant {}: now serialized", name), s, d);
+                }
+                _ => {}
+            }
+            match (
+                old_variant.json_name(o, false),
+                new_variant.json_name(n, false),
+            ) {
+                (Some(a), Some(b)) if a != b => {
+                    let (s, d) = only(
+                        NonBreaking,
+                        breaking_if(!new_variant.serde.aliases.contains(&a)),
+                    );
+                    self.push(
+                        id,
+                        format!("variant {}: deserialized name {:?} -> {:?}", name, a, b),
+                        s,
+                        d,
+                    );
+                }
+                (Some(_), None) => {
+                    let (s, d) = only(NonBreaking, Breaking);
+                    self.push(
+                        id,
+                        format!("variant {}: no longer deserialized", name),
+                        s,
+                        d,
+                    );
+                }
+                (None, Some(_)) => {
+                    let (s, d) = only(NonBreaking, NonBreaking);
+                    self.push(id, format!("variant {}: now deserialized", name), s, d);
+                }
+                _ => {}
+            }
+        }
+        // Old readers map unknown variants to the `#[serde(other)]` variant
+        let old_other = old_variants.iter().any(|v| v.serde.other);
+        for new_variant in &new_variants {
+            if !old_variants.iter().any(|v| v.name == new_variant.name) {
+                let (s, d) = only(breaking_if(!old_other), NonBreaking);
+                self.push(id, format!("variant {} added", new_variant.name), s, d);
+            }
+        }
+    }
+
+    fn compare_fields(
+        &mut self,
+        id: &str,
+        old: &Entry,
+        new: &Entry,
+        old_field: &Field,
+        new_field: &Field,
+        only: &dyn Fn(Impact, Impact) -> (Impact, Impact),
+    ) {
+        let (o, n) = (&old.serde, &new.serde);
+        let name = &new_field.name;
+
+        if !is_positional(new_field) {
+            match (old_field.json_key(o, true), new_field.json_key(n, true)) {
+                (Some(a), Some(b)) if a != b => {
+                    let (s, d) = only(Breaking, NonBreaking);
+                    self.push(
+                        id,
+                        format!("field {}: serialized key {:?} -> {:?}", name, a, b),
+                        s,
+                        d,
+                    );
+                }
+                (Some(_), None) => {
+                    let (s, d) = only(breaking_if(!old_field.is_optional(o)), NonBreaking);
+                    self.push(id, format!("field {}: no longer serialized", name), s, d);
+                }
+                (None, Some(_)) => {
+                    let (s, d) = only(breaking_if(o.deny_unknown_fields), NonBreaking);
+                    self.push(id, format!("field {}: serialized again", name), s, d);
+                }
+                _ => {}
+            }
+            match (old_field.json_key(o, false), new_field.json_key(n, false)) {
+                (Some(a), Some(b)) if a != b => {
+                    let aliased = new_field.serde.aliases.contains(&a);
+                    let (s, d) = only(NonBreaking, breaking_if(!aliased));
+                    self.push(
+                        id,
+                        format!(
+                            "field {}: deserialized key {:?} -> {:?}{}",
+                            name,
+                            a,
+                            b,
+                            if aliased { " (aliased)" } else { "" }
+                        ),
+                        s,
+                        d,
+                    );
+                }
+                (Some(_), None) => {
+                    let (s, d) = only(NonBreaking, breaking_if(n.deny_unknown_fields));
+                    self.push(id, format!("field {}: no longer deserialized", name), s, d);
+                }
+                (None, Some(_)) => {
+                    let (s, d) = only(NonBreaking, breaking_if(!new_field.is_optional(n)));
+                    self.push(id, format!("field {}: deserialized again", name), s, d);
+                }
+                _ => {}
+            }
+            for alias in &old_field.serde.aliases {
+                if !new_field.serde.aliases.contains(alias) {
+                    let (s, d) = only(NonBreaking, Breaking);
+                    self.push(
+                        id,
+                        format!("field {}: alias {:?} removed", name, alias),
+                        s,
+                        d,
+                    );
+                }
+            }
+        }
+
+        let (old_ty, new_ty) = (
+            wire_type(&old_field.ty, &self.renames),
+            wire_type(&new_field.ty, &BTreeMap::new()),
+        );
+        if old_ty != new_ty {
+            let (s, d) = if format!("Option<{}>", old_ty) == new_ty {
+                only(Breaking, NonBreaking)
+            } else if format!("Option<{}>", new_ty) == old_ty {
+                only(NonBreaking, Breaking)
+            } else {
+                only(Breaking, Breaking)
+            };
+            self.push(
+                id,
+                format!("field {}: type {} -> {}", name, old_field.ty, new_field.ty),
+                s,
+                d,
+            );
+        } else if !is_positional(new_field) && old_field.is_optional(o) && !new_field.is_optional(n)
+        {
+            let (s, d) = only(NonBreaking, Breaking);
+            self.push(id, format!("field {}: no longer optional", name), s, d);
+        }
+        if old_field.serde.flatten != new_field.serde.flatten {
+            let (s, d) = only(Breaking, Breaking);
+            self.push(
+                id,
+                format!(
+                    "field {}: flatten {} -> {}",
+                    name, old_field.serde.flatten, new_field.serde.flatten
+                ),
+                s,
+                d,
+            );
+        }
+        let (old_with, new_with) = (&old_field.serde.with, &new_field.serde.with);
+        if old_with != new_with {
+            let (s, d) = only(
+                breaking_if(old_with.ser != new_with.ser),
+                breaking_if(old_with.de != new_with.de),
+            );
+            self.push(
+                id,
+                format!(
+                    "field {}: custom serializer {:?} -> {:?}",
+                    name,
+                    old_with.ser.as_ref().or(old_with.de.as_ref()),
+                    new_with.ser.as_ref().or(new_with.de.as_ref())
+                ),
+                s,
+                d,
+            );
+        }
+    }
+
+    /// Whether a change breaks serialization or deserialization
+    pub fn breaking(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|f| f.ser == Breaking || f.de == Breaking)
+    }
+
+    /// Human readable report
+    pub fn to_text(&self) -> String {
+        if self.findings.is_empty() {
+            return "No wire format changes".to_string();
+        }
+        let mut lines = Vec::<String>::new();
+        let mut last_id = "";
+        for f in &self.findings {
+            if f.id != last_id {
+                lines.push(f.id.clone());
+                last_id = &f.id;
+            }
+            lines.push(format!(
+                "  ser: {:<12} de: {:<12} {}",
+                f.ser.to_string(),
+                f.de.to_string(),
+                f.description
+            ));
+        }
+        let count = |pick: &dyn Fn(&Finding) -> Impact| {
+            self.findings.iter().filter(|f| pick(f) == Breaking).count()
+        };
+        lines.push(format!(
+            "\n{} breaking change(s) for serialization, {} for deserialization",
+            count(&|f| f.ser),
+            count(&|f| f.de)
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Tuple struct or tuple variant field
+fn is_positional(field: &Field) -> bool {
+    field.ident().chars().all(|c| c.is_ascii_digit())
+}
+
+/// Field type as compared on the wire: without the paths and the serde-transparent pointers (`Box`, `Rc`, `Arc`,
+/// `Cow`), with the renamed types under their new name
+fn wire_type(ty: &str, renames: &BTreeMap<String, String>) -> String {
+    match syn::parse_str::<Type>(ty) {
+        Ok(ty) => unwrap_type(&ty, renames),
+        Err(_) => op::strip_paths(ty),
+    }
+}
+
+fn unwrap_type(ty: &Type, renames: &BTreeMap<String, String>) -> String {
+    match ty {
+        Type::Path(p) if p.qself.is_none() && !p.path.segments.is_empty() => {
+            let last = p.path.segments.last().unwrap();
+            let name = last.ident.to_string();
+            let args = op::generic_types(last);
+            if POINTERS.contains(&name.as_str()) && !args.is_empty() {
+                return unwrap_type(args[args.len() - 1], renames);
+            }
+            let name = renames.get(&name).cloned().unwrap_or(name);
+            if args.is_empty() {
+                name
+            } else {
+                let args = args
+                    .iter()
+                    .map(|t| unwrap_type(t, renames))
+                    .collect::<Vec<String>>();
+                format!("{}<{}>", name, args.join(", "))
+            }
+        }
+        Type::Reference(r) => unwrap_type(&r.elem, renames),
+        Type::Paren(p) => unwrap_type(&p.elem, renames),
+        Type::Group(g) => unwrap_type(&g.elem, renames),
+        Type::Slice(s) => format!("[{}]", unwrap_type(&s.elem, renames)),
+        Type::Array(a) => format!(
+            "[{}; {}]",
+            unwrap_type(&a.elem, renames),
+            op::expr_to_string(&a.len)
+        ),
+        Type::Tuple(t) => format!(
+            "({})",
+            t.elems
+                .iter()
+                .map(|t| unwrap_type(t, renames))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        _ => op::strip_paths(&op::type_to_string(ty)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::Source;
+
+    fn collection(src: &str) -> Collection {
+        let mut collection = Collection::new(Source {
+            crate_name: "test".to_string(),
+            path: "src".to_string(),
+            rev: None,
+            commit: None,
+            prefix: None,
+            repo_path: None,
+        });
+        collection.add_items(syn::parse_file(src).unwrap().items, "lib", "lib.rs");
+        collection.resolve();
+        collection
+    }
+
+    /// Serialization and deserialization impacts of the findings
+    fn compare(old: &str, new: &str) -> Vec<(String, Impact, Impact)> {
+        Report::new(&collection(old), &collection(new))
+            .findings
+            .into_iter()
+            .map(|f| (f.description, f.ser, f.de))
+            .collect()
+    }
+
+    fn finding(description: &str, ser: Impact, de: Impact) -> Vec<(String, Impact, Impact)> {
+        vec![(description.to_string(), ser, de)]
+    }
+
+    const POINT: &str = "#[derive(Serialize, Deserialize)] pub struct Point { pub x: u8 }";
+
+    #[test]
+    fn rename_breaks_both_directions() {
+        let new = "#[derive(Serialize, Deserialize)] pub struct Point { #[serde(rename = \"y\")] pub x: u8 }";
+        assert_eq!(
+            compare(POINT, new),
+            [
+                finding(
+                    "field x: serialized key \"x\" -> \"y\"",
+                    Breaking,
+                    NonBreaking
+                ),
+                finding(
+                    "field x: deserialized key \"x\" -> \"y\"",
+                    NonBreaking,
+                    Breaking
+                ),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn alias_keeps_deserialization() {
+        let new = "#[derive(Serialize, Deserialize)] pub struct Point { \
+            #[serde(rename = \"y\", alias = \"x\")] pub x: u8 }";
+        assert_eq!(
+            compare(POINT, new),
+            [
+                finding(
+                    "field x: serialized key \"x\" -> \"y\"",
+                    Breaking,
+                    NonBreaking
+                ),
+                finding(
+                    "field x: deserialized key \"x\" -> \"y\" (aliased)",
+                    NonBreaking,
+                    NonBreaking,
+                ),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn rename_of_one_direction() {
+        let new = "#[derive(Serialize, Deserialize)] pub struct Point { \
+            #[serde(rename(serialize = \"y\"))] pub x: u8 }";
+        assert_eq!(
+            compare(POINT, new),
+            finding(
+                "field x: serialized key \"x\" -> \"y\"",
+                Breaking,
+                NonBreaking
+            )
+        );
+        let new = "#[derive(Serialize, Deserialize)] pub struct Point { \
+            #[serde(rename(deserialize = \"y\"))] pub x: u8 }";
+        assert_eq!(
+            compare(POINT, new),
+            finding(
+                "field x: deserialized key \"x\" -> \"y\"",
+                NonBreaking,
+                Breaking
+            )
+        );
+    }
+
+    #[test]
+    fn new_required_field_breaks_deserialization() {
+        let new = "#[derive(Serialize, Deserialize)] pub struct Point { pub x: u8, pub y: u8 }";
+        assert_eq!(
+            compare(POINT, new),
+            finding("field y: u8 added", NonBreaking, Breaking)
+        );
+    }
+
+    #[test]
+    fn new_defaulted_field_is_compatible() {
+        let new = "#[derive(Serialize, Deserialize)] pub struct Point { \
+            pub x: u8, #[serde(default)] pub y: u8, pub z: Option<u8> }";
+        assert_eq!(
+            compare(POINT, new),
+            [
+                finding("field y: u8 added", NonBreaking, NonBreaking),
+                finding("field z: Option<u8> added", NonBreaking, NonBreaking),
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn new_field_breaks_serialization_with_deny_unknown_fields() {
+        let old = "#[derive(Serialize, Deserialize)] #[serde(deny_unknown_fields)] \
+            pub struct Point { pub x: u8 }";
+        let new = "#[derive(Serialize, Deserialize)] #[serde(deny_unknown_fields)] \
+            pub struct Point { pub x: u8, #[serde(default)] pub y: u8 }";
+        assert_eq!(
+            compare(old, new),
+            finding("field y: u8 added", Breaking, NonBreaking)
+        );
+    }
+
+    const SHAPE: &str =
+        "#[derive(Serialize, Deserialize)] pub enum Shape { Circle(u8), Square(u8) }";
+
+    #[test]
+    fn tagging_change_breaks_both_directions() {
+        let new = "#[derive(Serialize, Deserialize)] #[serde(tag = \"type\")] \
+            pub enum Shape { Circle(u8), Square(u8) }";
+        let findings = compare(SHAPE, new);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].0.starts_with("enum representation: "));
+        assert_eq!((findings[0].1, findings[0].2), (Breaking, Breaking));
+    }
+
+    #[test]
+    fn variant_removal_breaks_deserialization() {
+        let new = "#[derive(Serialize, Deserialize)] pub enum Shape { Circle(u8) }";
+        assert_eq!(
+            compare(SHAPE, new),
+            finding("variant Square removed", NonBreaking, Breaking)
+        );
+    }
+
+    #[test]
+    fn variant_addition_breaks_serialization() {
+        let new =
+            "#[derive(Serialize, Deserialize)] pub enum Shape { Circle(u8), Square(u8), Dot }";
+        assert_eq!(
+            compare(SHAPE, new),
+            finding("variant Dot added", Breaking, NonBreaking)
+        );
+    }
+
+    #[test]
+    fn wire_type_skips_transparent_pointers() {
+        let none = BTreeMap::new();
+        assert_eq!(wire_type("Box<block::Height>", &none), "Height");
+        assert_eq!(wire_type("Arc<Vec<Rc<u8>>>", &none), "Vec<u8>");
+        assert_eq!(wire_type("Cow<'static, str>", &none), "str");
+        assert_eq!(wire_type("&[Box<u8>; 4]", &none), "[u8; 4]");
+        assert_eq!(
+            wire_type("Option<(Box<u8>, String)>", &none),
+            "Option<(u8, String)>"
+        );
+        let mut renames = BTreeMap::new();
+        renames.insert("Old".to_string(), "New".to_string());
+        assert_eq!(wire_type("Option<Box<Old>>", &renames), "Option<New>");
+    }
+
+    #[test]
+    fn boxing_a_field_is_compatible() {
+        let old = "#[derive(Serialize, Deserialize)] pub struct List { pub next: Option<Node> } \
+            #[derive(Serialize, Deserialize)] pub struct Node { pub x: u8 }";
+        let new =
+            "#[derive(Serialize, Deserialize)] pub struct List { pub next: Option<Box<Node>> } \
+            #[derive(Serialize, Deserialize)] pub struct Node { pub x: u8 }";
+        assert_eq!(compare(old, new), []);
+    }
+}
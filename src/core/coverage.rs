@@ -0,0 +1,581 @@
+use crate::core::db::{Collection, Color, DataType, Location};
+use crate::core::graph::Graph;
+use crate::core::op;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::visit::{self, Visit};
+use syn::{Expr, Item, Pat, Type};
+
+/// Functions of the serde formats (`serde_json::to_string`, `toml::to_string`, ...) that serialize their argument
+const SERIALIZE: &[&str] = &[
+    "to_string",
+    "to_string_pretty",
+    "to_vec",
+    "to_vec_pretty",
+    "to_value",
+    "to_writer",
+    "to_writer_pretty",
+];
+
+/// Functions of the serde formats that deserialize into their turbofish or the annotated type
+const DESERIALIZE: &[&str] = &[
+    "from_str",
+    "from_slice",
+    "from_value",
+    "from_reader",
+    "from_bytes",
+];
+
+/// `Protobuf` and `prost::Message` methods that encode `self`
+const ENCODE: &[&str] = &[
+    "encode",
+    "encode_vec",
+    "encode_length_delimited",
+    "encode_length_delimited_vec",
+    "encode_to_vec",
+];
+
+/// `Protobuf` and `prost::Message` associated functions that decode into `Self`
+const DECODE: &[&str] = &[
+    "decode",
+    "decode_vec",
+    "decode_length_delimited",
+    "decode_length_delimited_vec",
+];
+
+/// Methods that keep the type of their receiver, like `from_str(..).unwrap()`
+const SAME_TYPE: &[&str] = &["unwrap", "expect", "clone", "to_owned", "unwrap_or_default"];
+
+/// What a test does with a type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Call {
+    Serialize,
+    Deserialize,
+    Protobuf,
+}
+
+/// A serialization call of a test
+#[derive(Debug, Clone, Serialize)]
+pub struct Use {
+    pub call: Call,
+    pub location: Location,
+}
+
+/// Serialization tests of a JSON serializable type
+#[derive(Debug, Serialize)]
+pub struct Tested {
+    pub id: String,
+    pub color: Color,
+    /// Custom `Serialize` or `Deserialize` implementation
+    pub custom: bool,
+    pub uses: Vec<Use>,
+}
+
+impl Tested {
+    fn calls(&self, call: Call) -> usize {
+        self.uses.iter().filter(|u| u.call == call).count()
+    }
+}
+
+/// Rust source of tests: a file of the `tests/` folder, or a source file of which only the `#[cfg(test)]` modules
+/// and `#[test]` functions are tests.
+pub struct TestFile {
+    /// Path in the locations of the report
+    pub path: String,
+    pub syntax: syn::File,
+    /// Id prefix of the types defined in the file, `None` outside of the source folder
+    pub module: Option<String>,
+    /// Every item is test code
+    pub whole: bool,
+}
+
+/// Files of the out-of-line `#[cfg(test)] mod name;` declarations of a source file, relative to the source folder
+pub fn test_modules(syntax: &syn::File, file: &str) -> Vec<String> {
+    let folder = match file.rsplit_once('/') {
+        Some((folder, "mod.rs" | "lib.rs" | "main.rs")) => format!("{}/", folder),
+        None if matches!(file, "mod.rs" | "lib.rs" | "main.rs") => String::new(),
+        _ => format!("{}/", file.strip_suffix(".rs").unwrap_or(file)),
+    };
+    syntax
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Mod(m) if m.content.is_none() && is_test(&m.attrs) => Some(&m.ident),
+            _ => None,
+        })
+        .flat_map(|name| {
+            vec![
+                format!("{}{}.rs", folder, name),
+                format!("{}{}/mod.rs", folder, name),
+            ]
+        })
+        .collect()
+}
+
+/// `#[cfg(test)]` or `#[test]`
+fn is_test(attrs: &[syn::Attribute]) -> bool {
+    op::is_ident_with_token_present(attrs, "cfg", "test") || op::is_ident_present(attrs, "test")
+}
+
+/// Which JSON serializable types are serialized, deserialized or protobuf encoded by the tests. The type names of the
+/// calls are resolved like the names on the command line, after expanding the `use` declarations of the file.
+#[derive(Debug, Serialize)]
+pub struct TestCoverage {
+    pub types: Vec<Tested>,
+    /// Type names of the calls that match several types
+    pub ambiguous: Vec<String>,
+}
+
+impl TestCoverage {
+    pub fn new(collection: &Collection, crate_name: &str, files: &[TestFile]) -> Self {
+        let mut scanner = Scanner {
+            graph: Graph::new(collection),
+            collection,
+            crate_name: crate_name.replace('-', "_"),
+            uses: BTreeMap::new(),
+            ambiguous: Vec::new(),
+            file: String::new(),
+            module: None,
+            imports: BTreeMap::new(),
+            variables: BTreeMap::new(),
+        };
+        for file in files {
+            scanner.file = file.path.clone();
+            scanner.module = file.module.clone();
+            scanner.imports = op::use_paths(&file.syntax.items);
+            for item in &file.syntax.items {
+                scanner.visit_test_item(item, file.whole);
+            }
+        }
+        let types = collection
+            .entries()
+            .filter(|(id, e)| {
+                e.public
+                    && e.r#type != DataType::Unknown
+                    && e.get_color() != Color::White
+                    && !id.contains('.')
+            })
+            .map(|(id, e)| Tested {
+                id: id.clone(),
+                color: e.get_color(),
+                custom: e.serializer || e.deserializer,
+                uses: scanner.uses.remove(id).unwrap_or_default(),
+            })
+            .collect();
+        Self {
+            types,
+            ambiguous: scanner.ambiguous,
+        }
+    }
+
+    /// Types without any serialization test
+    pub fn untested(&self) -> impl Iterator<Item = &Tested> {
+        self.types.iter().filter(|t| t.uses.is_empty())
+    }
+
+    /// The untested types, custom serializers first, and the totals
+    pub fn to_text(&self) -> String {
+        let mut untested = self.untested().collect::<Vec<&Tested>>();
+        untested.sort_by_key(|t| !t.custom);
+        let mut rows = vec![vec![
+            "untested type".to_string(),
+            "color".to_string(),
+            "custom".to_string(),
+        ]];
+        rows.extend(untested.iter().map(|t| {
+            vec![
+                t.id.clone(),
+                t.color.to_string(),
+                if t.custom { "custom serializer" } else { "" }.to_string(),
+            ]
+        }));
+        let mut widths = vec![0; rows[0].len()];
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.len());
+            }
+        }
+        let mut lines = Vec::new();
+        if !untested.is_empty() {
+            for row in &rows {
+                lines.push(
+                    row.iter()
+                        .enumerate()
+                        .map(|(i, cell)| format!("{:<width$}", cell, width = widths[i]))
+                        .collect::<Vec<String>>()
+                        .join("  ")
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+            lines.push(String::new());
+        }
+        lines.push(format!(
+            "{} of {} JSON serializable types have no serialization test ({} with a custom serializer)",
+            untested.len(),
+            self.types.len(),
+            untested.iter().filter(|t| t.custom).count()
+        ));
+        for name in &self.ambiguous {
+            lines.push(format!("warning: {}", name));
+        }
+        lines.join("\n")
+    }
+
+    /// Comma-separated table of every type with the number of calls, for spreadsheets
+    pub fn to_csv(&self) -> String {
+        let mut lines = vec!["id,color,custom,serialize,deserialize,protobuf".to_string()];
+        for t in &self.types {
+            lines.push(format!(
+                "{},{},{},{},{},{}",
+                t.id,
+                t.color,
+                t.custom,
+                t.calls(Call::Serialize),
+                t.calls(Call::Deserialize),
+                t.calls(Call::Protobuf)
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Whether a path segment names a type (or a variant), not a module or a function
+fn is_type_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// The paths of a type and of its generic arguments: `Vec<Height>` -> `Vec`, `Height`
+fn type_paths(ty: &Type) -> Vec<&syn::Path> {
+    match ty {
+        Type::Path(p) => {
+            let mut paths = vec![&p.path];
+            for segment in &p.path.segments {
+                paths.extend(op::generic_types(segment).into_iter().flat_map(type_paths));
+            }
+            paths
+        }
+        Type::Reference(r) => type_paths(&r.elem),
+        Type::Paren(p) => type_paths(&p.elem),
+        Type::Group(g) => type_paths(&g.elem),
+        Type::Slice(s) => type_paths(&s.elem),
+        Type::Array(a) => type_paths(&a.elem),
+        Type::Tuple(t) => t.elems.iter().flat_map(type_paths).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Segment names of a path
+fn names(path: &syn::Path) -> Vec<String> {
+    path.segments.iter().map(|s| s.ident.to_string()).collect()
+}
+
+/// Turbofish types of the last segment: `from_str::<Block>` -> `Block`
+fn turbofish(path: &syn::Path) -> Vec<&Type> {
+    path.segments.last().map_or(Vec::new(), op::generic_types)
+}
+
+/// Call of a function with the given names, through a serde format module (`serde_json::from_str`) or imported
+fn is_format_call(path: &syn::Path, functions: &[&str]) -> bool {
+    let names = names(path);
+    let n = names.len();
+    functions.contains(&names[n - 1].as_str()) && (n == 1 || !is_type_name(&names[n - 2]))
+}
+
+/// Call of an associated function with the given names: `Block::decode_vec`, `<Block as Protobuf<Raw>>::decode`.
+/// Returns the type.
+fn associated_call<'e>(call: &'e syn::ExprCall, functions: &[&str]) -> Option<TypeName<'e>> {
+    let path = match call.func.as_ref() {
+        Expr::Path(p) => p,
+        _ => return None,
+    };
+    let n = path.path.segments.len();
+    if !functions.contains(&path.path.segments[n - 1].ident.to_string().as_str()) {
+        return None;
+    }
+    if let Some(qself) = &path.qself {
+        return Some(TypeName::Type(&qself.ty));
+    }
+    if n >= 2 && is_type_name(&path.path.segments[n - 2].ident.to_string()) {
+        return Some(TypeName::Path(&path.path, n - 1));
+    }
+    None
+}
+
+/// A type in the code: a `syn::Type`, or the first segments of a path (`Block::new` -> `Block`)
+enum TypeName<'e> {
+    Type(&'e Type),
+    Path(&'e syn::Path, usize),
+}
+
+struct Scanner<'a> {
+    graph: Graph<'a>,
+    collection: &'a Collection,
+    crate_name: String,
+    uses: BTreeMap<String, Vec<Use>>,
+    ambiguous: Vec<String>,
+    file: String,
+    module: Option<String>,
+    imports: BTreeMap<String, Vec<String>>,
+    /// Types of the local variables of the current function
+    variables: BTreeMap<String, Vec<String>>,
+}
+
+impl<'a> Scanner<'a> {
+    /// Visit the test code of an item
+    fn visit_test_item(&mut self, item: &Item, whole: bool) {
+        if whole {
+            return self.visit_item(item);
+        }
+        match item {
+            Item::Fn(f) if is_test(&f.attrs) => self.visit_item_fn(f),
+            Item::Mod(m) if is_test(&m.attrs) => self.visit_item_mod(m),
+            Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    for item in items {
+                        self.visit_test_item(item, false);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Entry ids of a type name: expanded with the imports, without the crate prefix, resolved in the module of a
+    /// source file first
+    fn resolve(&mut self, names: &[String], location: &Location) -> Vec<String> {
+        let mut names = names.to_vec();
+        if let Some(path) = names.first().and_then(|first| self.imports.get(first)) {
+            names.splice(0..1, path.clone());
+        }
+        while let Some(first) = names.first() {
+            if matches!(first.as_str(), "crate" | "self" | "super") || *first == self.crate_name {
+                names.remove(0);
+            } else {
+                break;
+            }
+        }
+        let name = match names.last() {
+            Some(name) if is_type_name(name) => name.clone(),
+            _ => return Vec::new(),
+        };
+        if let Some(module) = &self.module {
+            let local = format!("{}::{}", module, name);
+            if names.len() == 1 && self.collection.get(&local).is_some() {
+                return vec![local];
+            }
+        }
+        let mut candidates = self.graph.candidates(&names.join("::"));
+        if candidates.is_empty() && names.len() > 1 {
+            // Re-exported under another module
+            candidates = self.graph.candidates(&name);
+        }
+        if candidates.len() > 1 {
+            self.ambiguous.push(format!(
+                "{}: type name {} is ambiguous, candidates: {}",
+                location,
+                names.join("::"),
+                candidates.join(", ")
+            ));
+            return Vec::new();
+        }
+        candidates
+    }
+
+    /// Record a call on a type
+    fn record(&mut self, ty: &TypeName, call: Call, span: proc_macro2::Span) {
+        let location = Location::new(&self.file, span);
+        let paths = match ty {
+            TypeName::Type(t) => type_paths(t)
+                .into_iter()
+                .map(names)
+                .collect::<Vec<Vec<String>>>(),
+            TypeName::Path(p, n) => vec![names(p)[..*n].to_vec()],
+        };
+        for path in paths {
+            for id in self.resolve(&path, &location) {
+                self.uses.entry(id).or_default().push(Use {
+                    call,
+                    location: location.clone(),
+                });
+            }
+        }
+    }
+
+    /// Record a call on the type of an expression
+    fn record_expr(&mut self, expr: &Expr, call: Call, span: proc_macro2::Span) {
+        for path in self.expr_type(expr) {
+            let location = Location::new(&self.file, span);
+            for id in self.resolve(&path, &location) {
+                self.uses.entry(id).or_default().push(Use {
+                    call,
+                    location: location.clone(),
+                });
+            }
+        }
+    }
+
+    /// Type paths of an expression, as far as they can be told without type inference
+    fn expr_type(&self, expr: &Expr) -> Vec<Vec<String>> {
+        match expr {
+            Expr::Struct(s) => vec![names(&s.path)],
+            Expr::Call(c) => match c.func.as_ref() {
+                Expr::Path(p) if is_format_call(&p.path, DESERIALIZE) => turbofish(&p.path)
+                    .into_iter()
+                    .flat_map(type_paths)
+                    .map(names)
+                    .collect(),
+                Expr::Path(p) => {
+                    let names = names(&p.path);
+                    let n = names.len();
+                    if n >= 2 && is_type_name(&names[n - 2]) {
+                        vec![names[..n - 1].to_vec()]
+                    } else if is_type_name(&names[n - 1]) {
+                        vec![names]
+                    } else {
+                        Vec::new()
+                    }
+                }
+                _ => Vec::new(),
+            },
+            Expr::Path(p) => {
+                let names = names(&p.path);
+                let n = names.len();
+                if n == 1 {
+                    self.variables.get(&names[0]).cloned().into_iter().collect()
+                } else if is_type_name(&names[n - 2]) {
+                    vec![names[..n - 1].to_vec()]
+                } else {
+                    Vec::new()
+                }
+            }
+            Expr::MethodCall(m) if SAME_TYPE.contains(&m.method.to_string().as_str()) => {
+                self.expr_type(&m.receiver)
+            }
+            Expr::Reference(r) => self.expr_type(&r.expr),
+            Expr::Paren(p) => self.expr_type(&p.expr),
+            Expr::Group(g) => self.expr_type(&g.expr),
+            Expr::Try(t) => self.expr_type(&t.expr),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Finds the deserialization calls without a turbofish, typed by a `let` annotation
+#[derive(Default)]
+struct Untyped {
+    calls: Vec<(Call, proc_macro2::Span)>,
+}
+
+impl<'ast> Visit<'ast> for Untyped {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let Expr::Path(p) = call.func.as_ref() {
+            let n = p.path.segments.len();
+            let last = p.path.segments[n - 1].ident.to_string();
+            if is_format_call(&p.path, DESERIALIZE) && turbofish(&p.path).is_empty() {
+                self.calls
+                    .push((Call::Deserialize, p.path.segments[n - 1].ident.span()));
+            } else if p.qself.is_none()
+                && DECODE.contains(&last.as_str())
+                && (n == 1 || p.path.segments[n - 2].ident == "Protobuf")
+            {
+                self.calls
+                    .push((Call::Protobuf, p.path.segments[n - 1].ident.span()));
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+}
+
+impl<'ast> Visit<'ast> for Scanner<'_> {
+    fn visit_item_fn(&mut self, f: &'ast syn::ItemFn) {
+        let variables = std::mem::take(&mut self.variables);
+        visit::visit_item_fn(self, f);
+        self.variables = variables;
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        visit::visit_local(self, local);
+        let (pat, ty) = match &local.pat {
+            Pat::Type(t) => (t.pat.as_ref(), Some(t.ty.as_ref())),
+            pat => (pat, None),
+        };
+        let init = local.init.as_ref().map(|(_, e)| e.as_ref());
+        if let (Some(ty), Some(init)) = (ty, init) {
+            let mut untyped = Untyped::default();
+            untyped.visit_expr(init);
+            for (call, span) in untyped.calls {
+                self.record(&TypeName::Type(ty), call, span);
+            }
+        }
+        if let Pat::Ident(ident) = pat {
+            let paths = match (ty, init) {
+                (Some(ty), _) => type_paths(ty).into_iter().take(1).map(names).collect(),
+                (None, Some(init)) => self.expr_type(init),
+                (None, None) => Vec::new(),
+            };
+            match paths.into_iter().next() {
+                Some(path) => self.variables.insert(ident.ident.to_string(), path),
+                None => self.variables.remove(&ident.ident.to_string()),
+            };
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        visit::visit_expr_call(self, call);
+        if let Some(ty) = associated_call(call, DECODE) {
+            self.record(&ty, Call::Protobuf, call.paren_token.span);
+        } else if let Some(ty) = associated_call(call, &["deserialize"]) {
+            self.record(&ty, Call::Deserialize, call.paren_token.span);
+        }
+        if let Some(ty) = associated_call(call, ENCODE) {
+            self.record(&ty, Call::Protobuf, call.paren_token.span);
+            for arg in &call.args {
+                self.record_expr(arg, Call::Protobuf, call.paren_token.span);
+            }
+        }
+        let path = match call.func.as_ref() {
+            Expr::Path(p) if p.qself.is_none() => &p.path,
+            _ => return,
+        };
+        if is_format_call(path, DESERIALIZE) {
+            for ty in turbofish(path) {
+                self.record(
+                    &TypeName::Type(ty),
+                    Call::Deserialize,
+                    call.paren_token.span,
+                );
+            }
+        } else if is_format_call(path, SERIALIZE) {
+            for arg in &call.args {
+                self.record_expr(arg, Call::Serialize, call.paren_token.span);
+            }
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        visit::visit_expr_method_call(self, call);
+        let method = call.method.to_string();
+        if ENCODE.contains(&method.as_str()) {
+            self.record_expr(&call.receiver, Call::Protobuf, call.method.span());
+        } else if method == "serialize" {
+            self.record_expr(&call.receiver, Call::Serialize, call.method.span());
+        }
+    }
+
+    /// Macro arguments that are expressions, like the ones of `assert_eq!`
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let args =
+            match Punctuated::<Expr, syn::Token![,]>::parse_terminated.parse2(mac.tokens.clone()) {
+                Ok(args) => args,
+                Err(_) => return,
+            };
+        for arg in &args {
+            self.visit_expr(arg);
+        }
+    }
+}
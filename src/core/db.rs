@@ -1,29 +1,46 @@
-use crate::core::consts;
+use crate::core::attrs::{self, ContainerAttrs, FieldAttrs, VariantAttrs};
+use crate::core::graph::Graph;
 use crate::core::op;
+use crate::core::template::{Extras, Template};
 
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::ops::Deref;
+use std::path::Path;
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
 use syn::{Attribute, Fields, Item, Visibility};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum DataType {
     Enum,
     Struct,
     Unknown,
 }
 
-impl ToString for DataType {
-    fn to_string(&self) -> String {
-        match &self {
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match &self {
             DataType::Enum => "ellipse",
             DataType::Struct => "rectangle",
             DataType::Unknown => "rhombus",
+        })
+    }
+}
+
+impl DataType {
+    /// Legend line of the shape
+    pub fn description(&self) -> &'static str {
+        match &self {
+            DataType::Enum => "Ellipse: enum",
+            DataType::Struct => "Rounded rectangle: struct",
+            DataType::Unknown => "Rhombus: unknown type",
         }
-        .to_string()
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Color {
     /// Invalid struct or enum
     Red,
@@ -43,9 +60,9 @@ pub enum Color {
     BlueGradient,
 }
 
-impl ToString for Color {
-    fn to_string(&self) -> String {
-        match &self {
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match &self {
             Color::Red => "red",
             Color::White => "white",
             Color::Green => "green",
@@ -54,27 +71,526 @@ impl ToString for Color {
             Color::YellowGradient => "yellow_gradient",
             Color::Blue => "blue",
             Color::BlueGradient => "blue_gradient",
+        })
+    }
+}
+
+/// Serialized with the style name, like in the CSV
+impl Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Color {
+    /// Color name without the gradient
+    pub fn base(&self) -> &'static str {
+        match &self {
+            Color::Red => "red",
+            Color::White => "white",
+            Color::Green | Color::GreenGradient => "green",
+            Color::Yellow | Color::YellowGradient => "yellow",
+            Color::Blue | Color::BlueGradient => "blue",
+        }
+    }
+
+    /// Asymmetric serialization
+    pub fn is_gradient(&self) -> bool {
+        matches!(
+            &self,
+            Color::GreenGradient | Color::YellowGradient | Color::BlueGradient
+        )
+    }
+
+    /// Capitalized base color name
+    pub fn title(&self) -> String {
+        let base = self.base();
+        format!("{}{}", base[..1].to_uppercase(), &base[1..])
+    }
+
+    /// Legend line of the base color
+    pub fn description(&self) -> &'static str {
+        match self.base() {
+            "red" => "invalid combination of features",
+            "green" => "#[derive(Deserialize, Serialize)]",
+            "blue" => "#[serde(try_from = \"\", into = \"\")]",
+            "yellow" => "impl Deserialize/Serialize for my_struct {}",
+            _ => "No serialization",
+        }
+    }
+}
+
+/// Serialization frameworks of a type, drawn as the border style of its node
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Encoding {
+    /// No serialization
+    None,
+    /// serde only
+    Json,
+    /// prost message or conversions to and from protobuf types only
+    Proto,
+    /// serde and protobuf
+    Both,
+}
+
+impl Encoding {
+    /// Suffix of the node style name: the JSON-only styles keep the color name
+    pub fn suffix(&self) -> &'static str {
+        match &self {
+            Encoding::None | Encoding::Json => "",
+            Encoding::Proto => "_proto",
+            Encoding::Both => "_both",
+        }
+    }
+
+    /// Legend line of the border style. The white nodes keep the default border and have no legend line.
+    pub fn description(&self) -> Option<&'static str> {
+        match &self {
+            Encoding::None => None,
+            Encoding::Json => Some("Thin border: JSON only"),
+            Encoding::Proto => Some("Dashed border: protobuf only"),
+            Encoding::Both => Some("Thick border: JSON and protobuf"),
+        }
+    }
+}
+
+/// Path of a type name after expanding the `use` declarations of its file: `RawBlock` ->
+/// `tendermint_proto::types::Block`
+fn use_path(ty: &str, imports: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let mut segments = ty
+        .split('<')
+        .next()
+        .unwrap_or(ty)
+        .split("::")
+        .map(|s| s.trim().to_string())
+        .collect::<Vec<String>>();
+    if let Some(path) = segments.first().and_then(|first| imports.get(first)) {
+        segments.splice(0..1, path.clone());
+    }
+    segments
+}
+
+/// Whether a type path is in `tendermint_proto` or in a `pb` module. The other protobuf types are the prost entries.
+fn is_proto_path(path: &[String]) -> bool {
+    path.iter().any(|s| s == "tendermint_proto" || s == "pb")
+}
+
+/// `impl TryFrom<Raw> for T` or `impl From<T> for Raw` whose `Raw` side may be a prost entry, known after all the
+/// files are added
+#[derive(Debug, Clone)]
+pub(crate) struct Conversion {
+    /// Entry of the domain type
+    id: String,
+    raw: String,
+    /// `Raw` -> `T`
+    from: bool,
+}
+
+/// How many values of a type a field holds
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Multiplicity {
+    /// Plain or boxed value
+    One,
+    /// `Option`
+    Optional,
+    /// `Vec`, slices, arrays and sets
+    Many,
+    /// Map keys or values
+    Keyed,
+}
+
+impl fmt::Display for Multiplicity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match &self {
+            Multiplicity::One => "1",
+            Multiplicity::Optional => "0..1",
+            Multiplicity::Many => "0..*",
+            Multiplicity::Keyed => "keyed",
+        })
+    }
+}
+
+/// Where an item, field or impl is defined
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    /// File path relative to the source folder
+    pub file: String,
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+}
+
+impl Location {
+    pub fn new(file: &str, span: proc_macro2::Span) -> Self {
+        let start = span.start();
+        Self {
+            file: file.to_string(),
+            line: start.line,
+            column: start.column + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// A named (or numbered) field of a struct or enum variant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Field {
+    /// `field`, `0` for tuple fields, `Variant.field` or `Variant.0` in enums and `Variant` for unit variants
+    pub name: String,
+    /// The type as written in the source, empty for unit variants
+    pub ty: String,
+    pub serde: FieldAttrs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// `///` comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+    /// `pub` field (the fields of enum variants are public)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub public: bool,
+    /// `#[prost(...)]` attribute of a protobuf message field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prost: Option<String>,
+}
+
+/// Moving or documenting a field doesn't change it
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.ty == other.ty && self.serde == other.serde
+    }
+}
+
+impl Field {
+    /// Field name without the variant
+    pub fn ident(&self) -> &str {
+        self.name.rsplit('.').next().unwrap()
+    }
+
+    /// Enum variant of the field
+    pub fn variant(&self) -> Option<&str> {
+        self.name.split_once('.').map(|(v, _)| v)
+    }
+
+    /// JSON key of a named field when serializing (or deserializing). `None` if the field is skipped.
+    pub fn json_key(&self, container: &ContainerAttrs, serialize: bool) -> Option<String> {
+        let (skip, rename, rule) = if serialize {
+            (
+                self.serde.skip_serializing,
+                &self.serde.rename.ser,
+                &container.rename_all.ser,
+            )
+        } else {
+            (
+                self.serde.skip_deserializing,
+                &self.serde.rename.de,
+                &container.rename_all.de,
+            )
+        };
+        if skip {
+            return None;
+        }
+        if let Some(rename) = rename {
+            return Some(rename.clone());
+        }
+        // The container `rename_all` of an enum applies to the variants, not to their fields.
+        match rule {
+            Some(rule) if self.variant().is_none() => Some(attrs::rename_field(self.ident(), rule)),
+            _ => Some(self.ident().to_string()),
+        }
+    }
+
+    /// Serde markers of the field: `[json: key]` if the key differs from the field name, `[skip]`, `[flatten]`,
+    /// `[default]` and the custom serializers
+    pub fn markers(&self, container: &ContainerAttrs) -> Vec<String> {
+        let mut markers = Vec::new();
+        let keys = (
+            self.json_key(container, true),
+            self.json_key(container, false),
+        );
+        match keys {
+            (Some(ser), Some(de)) if ser != de => markers.push(format!("json: {} / {}", ser, de)),
+            (Some(key), _) | (None, Some(key)) if key != self.ident() => {
+                markers.push(format!("json: {}", key))
+            }
+            _ => {}
+        }
+        match (self.serde.skip_serializing, self.serde.skip_deserializing) {
+            (true, true) => markers.push("skip".to_string()),
+            (true, false) => markers.push("skip_serializing".to_string()),
+            (false, true) => markers.push("skip_deserializing".to_string()),
+            _ => {}
+        }
+        if let Some(condition) = &self.serde.skip_serializing_if {
+            markers.push(format!("skip_serializing_if: {}", condition));
+        }
+        if self.serde.flatten {
+            markers.push("flatten".to_string());
+        }
+        if self.serde.default {
+            markers.push("default".to_string());
+        }
+        match (&self.serde.with.ser, &self.serde.with.de) {
+            (Some(ser), Some(de)) if ser == de => markers.push(format!("with: {}", ser)),
+            (ser, de) => {
+                if let Some(ser) = ser {
+                    markers.push(format!("serialize_with: {}", ser));
+                }
+                if let Some(de) = de {
+                    markers.push(format!("deserialize_with: {}", de));
+                }
+            }
+        }
+        if let Some(prost) = &self.prost {
+            markers.push(format!("prost: {}", prost));
+        }
+        markers
+    }
+
+    /// `name: Type [markers]` line of a class label, HTML-escaped
+    fn class_line(&self, name: &str, container: &ContainerAttrs) -> String {
+        let markers = self
+            .markers(container)
+            .iter()
+            .map(|m| format!(" [{}]", m))
+            .collect::<String>();
+        escape_html(&format!("{}: {}{}", name, self.ty, markers))
+    }
+
+    /// The field can be missing from the JSON input: `Option`, `default` or skipped.
+    pub fn is_optional(&self, container: &ContainerAttrs) -> bool {
+        (self.ty.starts_with("Option<") && self.serde.with.de.is_none())
+            || self.serde.default
+            || self.serde.skip_deserializing
+            || container.default
+    }
+}
+
+/// Shape of an enum variant
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum VariantKind {
+    /// `Variant`
+    Unit,
+    /// `Variant(T)`
+    Tuple,
+    /// `Variant { field: T }`
+    Struct,
+}
+
+/// An enum variant. The payload fields are in the `members` of the entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    pub kind: VariantKind,
+    /// Explicit discriminant: `Variant = 1`
+    pub discriminant: Option<String>,
+    pub serde: VariantAttrs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// `///` comment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<String>,
+}
+
+impl Variant {
+    /// JSON name of the variant when serializing (or deserializing). `None` if the variant is skipped.
+    pub fn json_name(&self, container: &ContainerAttrs, serialize: bool) -> Option<String> {
+        let (skip, rename, rule) = if serialize {
+            (
+                self.serde.skip_serializing,
+                &self.serde.rename.ser,
+                &container.rename_all.ser,
+            )
+        } else {
+            (
+                self.serde.skip_deserializing,
+                &self.serde.rename.de,
+                &container.rename_all.de,
+            )
+        };
+        if skip {
+            None
+        } else if let Some(rename) = rename {
+            Some(rename.clone())
+        } else if let Some(rule) = rule {
+            Some(attrs::rename_variant(&self.name, rule))
+        } else {
+            Some(self.name.clone())
+        }
+    }
+
+    /// Serde markers of the variant, like the field markers
+    pub fn markers(&self, container: &ContainerAttrs) -> Vec<String> {
+        let mut markers = Vec::new();
+        match (
+            self.json_name(container, true),
+            self.json_name(container, false),
+        ) {
+            (Some(ser), Some(de)) if ser != de => markers.push(format!("json: {} / {}", ser, de)),
+            (Some(key), _) | (None, Some(key)) if key != self.name => {
+                markers.push(format!("json: {}", key))
+            }
+            _ => {}
+        }
+        match (self.serde.skip_serializing, self.serde.skip_deserializing) {
+            (true, true) => markers.push("skip".to_string()),
+            (true, false) => markers.push("skip_serializing".to_string()),
+            (false, true) => markers.push("skip_deserializing".to_string()),
+            _ => {}
+        }
+        if self.serde.other {
+            markers.push("other".to_string());
+        }
+        if let Some(with) = self.serde.with.ser.as_ref().or(self.serde.with.de.as_ref()) {
+            markers.push(format!("with: {}", with));
         }
-        .to_string()
+        markers
+    }
+}
+
+/// A resolved dependency between two entries: `from` has a field that references `to`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    /// Solid (serialization) dependency, otherwise dashed
+    pub solid: bool,
+    /// Names of the fields of `from` that reference `to`
+    #[serde(default)]
+    pub fields: Vec<String>,
+    /// Text shown on the edge in the diagrams
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl Edge {
+    /// Name of the node that carries the label in the diagrams
+    pub fn label_node(&self) -> String {
+        format!("{} -> {}", self.from, self.to)
+    }
+}
+
+/// A problem found while analyzing the source code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub id: String,
+    /// The field that could not be resolved
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.id, self.message)
+    }
+}
+
+/// Collapsed neighbors of an entry on the border of a focused view
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stub {
+    pub id: String,
+    /// The hidden entries embed `id`, instead of being used by it
+    pub upstream: bool,
+    pub count: usize,
+}
+
+impl Stub {
+    /// Node name in the diagram
+    pub fn name(&self) -> String {
+        let kind = match (self.upstream, self.count) {
+            (true, 1) => "dependent",
+            (true, _) => "dependents",
+            (false, 1) => "dependency",
+            (false, _) => "dependencies",
+        };
+        format!("+{} more {} of {}", self.count, kind, self.id)
     }
 }
 
-#[derive(Debug)]
+/// Where the entries of a collection come from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Source {
+    pub crate_name: String,
+    /// Source folder or file
+    pub path: String,
+    /// Git revision as given on the command line
+    pub rev: Option<String>,
+    /// Commit id of the git revision
+    pub commit: Option<String>,
+    /// Prefix of the entry ids after merging (the crate name), `None` if the ids are not qualified
+    pub prefix: Option<String>,
+    /// Folder of the source files in their git repository, `None` outside of a repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entry {
-    public: bool,
-    r#type: DataType,
-    serialize: bool,
-    deserialize: bool,
-    serde_from: bool,
-    serde_into: bool,
+    pub(crate) public: bool,
+    pub(crate) r#type: DataType,
+    pub(crate) serialize: bool,
+    pub(crate) deserialize: bool,
+    pub(crate) serde_from: bool,
+    pub(crate) serde_into: bool,
+
+    pub(crate) serializer: bool,
+    pub(crate) deserializer: bool,
+
+    pub(crate) serde_custom_field: bool,
+    pub(crate) fields: Vec<String>,
+    pub(crate) members: Vec<Field>,
+    pub(crate) serde: ContainerAttrs,
+    /// Variants of an enum
+    #[serde(default)]
+    pub(crate) variants: Vec<Variant>,
+    /// Definition of the struct or enum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) location: Option<Location>,
+    /// The `impl Serialize` and `impl Deserialize` blocks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) impls: Vec<Location>,
+    /// `///` comment of the struct or enum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) doc: Option<String>,
+    /// `#[derive(PartialEq)]`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) partial_eq: bool,
+    /// `#[derive(prost::Message)]`, `prost::Oneof` or `prost::Enumeration`: a protobuf type itself
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) prost: bool,
+    /// The `Raw` types of the `impl Protobuf<Raw>` blocks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) protobuf: Vec<String>,
+    /// Protobuf types of the `impl TryFrom<Raw>` (or `From<Raw>`) blocks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) proto_from: Vec<String>,
+    /// Protobuf types of the `impl From<T> for Raw` (or `TryFrom<T>`) blocks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) proto_into: Vec<String>,
+}
 
-    serializer: bool,
-    deserializer: bool,
+/// A resolved dependency: the target entry id and the field type ident that refers to it
+type Target = (String, String);
 
-    serde_custom_field: bool,
-    fields: Vec<String>,
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Collection {
+    pub(crate) sources: Vec<Source>,
+    pub(crate) entries: BTreeMap<String, Entry>,
+    /// Dependencies of the public entries, filled in by `resolve`
+    pub(crate) edges: Vec<Edge>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    /// Elided neighbors of a focused view
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) stubs: Vec<Stub>,
+    /// Conversions to be matched with the prost entries by `resolve`
+    #[serde(skip)]
+    pub(crate) conversions: Vec<Conversion>,
 }
-pub struct Collection(HashMap<String, Entry>);
 
 impl Entry {
     /// constructor
@@ -92,9 +608,60 @@ impl Entry {
 
             serde_custom_field: false,
             fields: vec![],
+            members: vec![],
+            serde: ContainerAttrs::default(),
+            variants: vec![],
+            location: None,
+            impls: vec![],
+            doc: None,
+            partial_eq: false,
+            prost: false,
+            protobuf: vec![],
+            proto_from: vec![],
+            proto_into: vec![],
         }
     }
 
+    /// Definition of the struct or enum
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// `///` comment of the struct or enum
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+
+    /// The docs of the entry, its variants and fields on one line, for the draw.io tooltip
+    pub fn tooltip(&self) -> String {
+        let one_line = |doc: &str| doc.split_whitespace().collect::<Vec<&str>>().join(" ");
+        let mut parts = self
+            .doc
+            .iter()
+            .map(|d| one_line(d))
+            .collect::<Vec<String>>();
+        for v in &self.variants {
+            if let Some(doc) = &v.doc {
+                parts.push(format!("{}: {}", v.name, one_line(doc)));
+            }
+        }
+        for f in &self.members {
+            if let Some(doc) = &f.doc {
+                parts.push(format!("{}: {}", f.name, one_line(doc)));
+            }
+        }
+        for (what, types) in [
+            ("Protobuf", &self.protobuf),
+            ("from", &self.proto_from),
+            ("into", &self.proto_into),
+        ] {
+            if !types.is_empty() {
+                parts.push(format!("{}: {}", what, types.join(", ")));
+            }
+        }
+        parts.join(" | ")
+    }
+
     /// Fill in the basic values based on the input
     pub fn complete_basics(&mut self, vis: &Visibility, attrs: &[Attribute]) {
         if let Visibility::Public(_) = vis {
@@ -102,16 +669,75 @@ impl Entry {
         }
         self.serialize = op::is_ident_with_token_present(attrs, "derive", "Serialize");
         self.deserialize = op::is_ident_with_token_present(attrs, "derive", "Deserialize");
+        self.partial_eq = op::is_ident_with_token_present(attrs, "derive", "PartialEq");
+        self.prost = ["prost", "Message", "Oneof", "Enumeration"]
+            .iter()
+            .any(|t| op::is_ident_with_token_present(attrs, "derive", t));
         if op::is_ident_present(attrs, "serde") {
-            self.serde_from = op::is_ident_with_token_present(&attrs, "serde", "try_from")
+            self.serde_from = op::is_ident_with_token_present(attrs, "serde", "try_from")
                 || op::is_ident_with_token_present(attrs, "serde", "from");
             self.serde_into = op::is_ident_with_token_present(attrs, "serde", "into");
-            // Todo: Also save the type.
         }
+        self.serde = ContainerAttrs::new(attrs);
+        self.doc = attrs::doc(attrs);
+    }
+
+    /// Add an enum variant with its payload fields.
+    pub fn complete_variant(&mut self, variant: syn::Variant, file: &str) {
+        let name = variant.ident.unraw().to_string();
+        self.variants.push(Variant {
+            name: name.clone(),
+            kind: match &variant.fields {
+                Fields::Unit => VariantKind::Unit,
+                Fields::Unnamed(_) => VariantKind::Tuple,
+                Fields::Named(_) => VariantKind::Struct,
+            },
+            discriminant: variant
+                .discriminant
+                .as_ref()
+                .map(|(_, expr)| op::expr_to_string(expr)),
+            serde: VariantAttrs::new(&variant.attrs),
+            location: Some(Location::new(file, variant.ident.span())),
+            doc: attrs::doc(&variant.attrs),
+        });
+        self.complete_fields(Some(&name), variant.fields, file);
+    }
+
+    /// The variants of an enum. Snapshots without variant data get them from the members.
+    pub fn get_variants(&self) -> Vec<Variant> {
+        if !self.variants.is_empty() || self.r#type != DataType::Enum {
+            return self.variants.clone();
+        }
+        let mut variants = Vec::<Variant>::new();
+        for f in &self.members {
+            let name = f.variant().unwrap_or(&f.name);
+            if variants.iter().any(|v| v.name == name) {
+                continue;
+            }
+            variants.push(Variant {
+                name: name.to_string(),
+                kind: if f.variant().is_none() {
+                    VariantKind::Unit
+                } else if f.ident().parse::<usize>().is_ok() {
+                    VariantKind::Tuple
+                } else {
+                    VariantKind::Struct
+                },
+                discriminant: None,
+                serde: VariantAttrs::default(),
+                location: f.location.clone(),
+                doc: None,
+            });
+        }
+        variants
     }
 
-    /// Fill in the `fields` vector based on the input
-    pub fn complete_fields(&mut self, fields: Fields) {
+    /// Fill in the `fields` and `members` vectors based on the input. Enum variants set the `variant` name.
+    pub fn complete_fields(&mut self, variant: Option<&str>, fields: Fields, file: &str) {
+        let member_name = |name: String| match variant {
+            None => name,
+            Some(v) => format!("{}.{}", v, name),
+        };
         match fields {
             Fields::Named(n) => {
                 if n.named
@@ -121,6 +747,15 @@ impl Entry {
                     self.serde_custom_field = true;
                 }
                 for f in n.named {
+                    self.members.push(Field {
+                        name: member_name(f.ident.as_ref().unwrap().unraw().to_string()),
+                        ty: op::type_to_string(&f.ty),
+                        serde: FieldAttrs::new(&f.attrs),
+                        location: f.ident.as_ref().map(|i| Location::new(file, i.span())),
+                        doc: attrs::doc(&f.attrs),
+                        public: variant.is_some() || matches!(f.vis, Visibility::Public(_)),
+                        prost: attrs::prost(&f.attrs),
+                    });
                     self.add_to_fields(op::get_idents_from_types(&f.ty));
                 }
             }
@@ -131,11 +766,32 @@ impl Entry {
                 {
                     self.serde_custom_field = true;
                 }
-                for f in u.unnamed {
+                for (i, f) in u.unnamed.into_iter().enumerate() {
+                    self.members.push(Field {
+                        name: member_name(i.to_string()),
+                        ty: op::type_to_string(&f.ty),
+                        serde: FieldAttrs::new(&f.attrs),
+                        location: Some(Location::new(file, f.ty.span())),
+                        doc: attrs::doc(&f.attrs),
+                        public: variant.is_some() || matches!(f.vis, Visibility::Public(_)),
+                        prost: attrs::prost(&f.attrs),
+                    });
                     self.add_to_fields(op::get_idents_from_types(&f.ty));
                 }
             }
-            Fields::Unit => {}
+            Fields::Unit => {
+                if let Some(v) = variant {
+                    self.members.push(Field {
+                        name: v.to_string(),
+                        ty: String::new(),
+                        serde: FieldAttrs::default(),
+                        location: None,
+                        doc: None,
+                        public: true,
+                        prost: None,
+                    });
+                }
+            }
         }
     }
 
@@ -150,6 +806,100 @@ impl Entry {
         self.fields = new_fields;
     }
 
+    /// Names of the fields that have `ident` in their type
+    pub fn members_with_ident(&self, ident: &str) -> Vec<String> {
+        self.members
+            .iter()
+            .filter(|f| {
+                syn::parse_str::<syn::Type>(&f.ty)
+                    .map(|ty| op::get_idents_from_types(&ty).iter().any(|i| i == ident))
+                    .unwrap_or(false)
+            })
+            .map(|f| f.name.clone())
+            .collect()
+    }
+
+    /// Lines of a UML class-style label: the fields of a struct, the variants of an enum with their payloads
+    pub fn class_lines(&self) -> Vec<String> {
+        if self.r#type != DataType::Enum {
+            return self
+                .members
+                .iter()
+                .map(|f| f.class_line(&f.name, &self.serde))
+                .collect();
+        }
+        let mut lines = vec![format!("<i>{}</i>", self.serde.tagging())];
+        for variant in self.get_variants() {
+            let name = variant.name.as_str();
+            let payload = self
+                .members
+                .iter()
+                .filter(|f| f.variant() == Some(name))
+                .collect::<Vec<&Field>>();
+            let mut suffix = variant
+                .markers(&self.serde)
+                .iter()
+                .map(|m| format!(" [{}]", m))
+                .collect::<String>();
+            if let Some(discriminant) = &variant.discriminant {
+                suffix.push_str(format!(" = {}", discriminant).as_str());
+            }
+            match variant.kind {
+                VariantKind::Unit => lines.push(escape_html(&format!("{}{}", name, suffix))),
+                VariantKind::Tuple => {
+                    let types = payload
+                        .iter()
+                        .map(|f| f.ty.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ");
+                    lines.push(escape_html(&format!("{}({}){}", name, types, suffix)));
+                }
+                VariantKind::Struct => {
+                    lines.push(escape_html(&format!("{} {{{}", name, suffix)));
+                    for f in payload {
+                        lines.push(format!(
+                            "&nbsp;&nbsp;{}",
+                            f.class_line(f.ident(), &ContainerAttrs::default())
+                        ));
+                    }
+                    lines.push("}".to_string());
+                }
+            }
+        }
+        lines
+    }
+
+    /// Field names with the multiplicity of the `to` type: `header: 1`, `evidence: 0..*`
+    pub fn field_labels(&self, fields: &[String], to: &str) -> Vec<String> {
+        let name = to.rsplit("::").next().unwrap();
+        self.members
+            .iter()
+            .filter(|f| fields.contains(&f.name))
+            .map(|f| {
+                let multiplicity = syn::parse_str::<syn::Type>(&f.ty)
+                    .ok()
+                    .and_then(|ty| op::get_multiplicity(&ty, name))
+                    .unwrap_or(Multiplicity::One);
+                format!("{}: {}", f.name, multiplicity)
+            })
+            .collect()
+    }
+
+    /// JSON (serde), protobuf (prost and the conversions to the protobuf types) or both
+    pub fn get_encoding(&self) -> Encoding {
+        let json = self.get_color() != Color::White;
+        let proto = self.prost
+            || !self.protobuf.is_empty()
+            || !self.proto_from.is_empty()
+            || !self.proto_into.is_empty();
+        match (json, proto) {
+            (false, false) => Encoding::None,
+            (true, false) => Encoding::Json,
+            (false, true) => Encoding::Proto,
+            (true, true) => Encoding::Both,
+        }
+    }
+
     /// Get the color (serialization type) of the entry
     pub fn get_color(&self) -> Color {
         let derive = self.serialize || self.deserialize;
@@ -186,14 +936,74 @@ impl Entry {
 
 impl Collection {
     /// constructor
-    pub fn new() -> Self {
-        Self(HashMap::<String, Entry>::new())
+    pub fn new(source: Source) -> Self {
+        Self {
+            sources: vec![source],
+            ..Self::default()
+        }
+    }
+
+    /// All entries, ordered by id
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &Entry)> {
+        self.entries.iter()
+    }
+
+    /// Get an entry by id
+    pub fn get(&self, id: &str) -> Option<&Entry> {
+        self.entries.get(id)
+    }
+
+    /// Resolved dependencies of the public entries
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Collapsed neighbors of a focused view
+    pub fn stubs(&self) -> &[Stub] {
+        &self.stubs
+    }
+
+    /// Problems found while resolving the dependencies
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// A copy of the collection with only the given entries and the edges between them
+    pub fn subset(&self, ids: &BTreeSet<String>) -> Collection {
+        Collection {
+            sources: self.sources.clone(),
+            entries: self
+                .entries
+                .iter()
+                .filter(|(id, _)| ids.contains(*id))
+                .map(|(id, e)| (id.clone(), e.clone()))
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .filter(|e| ids.contains(&e.from) && ids.contains(&e.to))
+                .cloned()
+                .collect(),
+            diagnostics: self
+                .diagnostics
+                .iter()
+                .filter(|d| ids.contains(&d.id))
+                .cloned()
+                .collect(),
+            stubs: self
+                .stubs
+                .iter()
+                .filter(|s| ids.contains(&s.id))
+                .cloned()
+                .collect(),
+            conversions: vec![],
+        }
     }
 
     /// Return (get/create/fix) a mutable entry from the collection. If the entry doesn't exist, create it, if the entry type is invalid, fix it.
     pub fn spawn_entry(&mut self, id: &str, new_type: DataType) -> &mut Entry {
         let entry = self
-            .0
+            .entries
             .entry(id.to_string())
             .or_insert_with(|| Entry::new(new_type));
         if let DataType::Unknown = entry.r#type {
@@ -202,45 +1012,87 @@ impl Collection {
         entry
     }
 
-    /// Add Rust tokens into the collection.
-    pub fn add_items(&mut self, items: Vec<Item>, id_prefix: &str) {
+    /// Add Rust tokens into the collection. `file` is the path of the source file, relative to the source folder.
+    pub fn add_items(&mut self, items: Vec<Item>, id_prefix: &str, file: &str) {
+        let imports = op::use_paths(&items);
         for item in items {
             match item {
                 Item::Enum(e) => {
                     let id = format!("{}::{}", id_prefix, e.ident);
                     let entry = self.spawn_entry(&id, DataType::Enum);
                     entry.complete_basics(&e.vis, &e.attrs);
+                    entry.location = Some(Location::new(file, e.ident.span()));
                     for variant in e.variants {
-                        entry.complete_fields(variant.fields);
+                        entry.complete_variant(variant, file);
                     }
                 }
                 Item::Struct(e) => {
                     let id = format!("{}::{}", id_prefix, e.ident);
                     let entry = self.spawn_entry(&id, DataType::Struct);
                     entry.complete_basics(&e.vis, &e.attrs);
-                    entry.complete_fields(e.fields);
+                    entry.location = Some(Location::new(file, e.ident.span()));
+                    entry.complete_fields(None, e.fields, file);
                 }
                 Item::Impl(i) => {
-                    let impl_trait = match i
-                        .trait_
-                        .as_ref()
-                        .map(|(_, path, _)| op::get_idents_from_paths(path).last().cloned())
-                        .flatten()
-                    {
-                        None => continue,
-                        Some(t) => t,
-                    };
+                    let impl_trait =
+                        match i.trait_.as_ref().and_then(|(_, path, _)| {
+                            op::get_idents_from_paths(path).last().cloned()
+                        }) {
+                            None => continue,
+                            Some(t) => t,
+                        };
                     let impl_ident =
                         match op::get_idents_from_types(i.self_ty.deref()).last().cloned() {
                             None => continue,
                             Some(n) => n,
                         };
                     let id = format!("{}::{}", id_prefix, impl_ident);
+                    let location = Location::new(file, i.impl_token.span);
                     match impl_trait.as_str() {
                         "Deserialize" => {
-                            self.spawn_entry(&id, DataType::Unknown).deserializer = true
+                            let entry = self.spawn_entry(&id, DataType::Unknown);
+                            entry.deserializer = true;
+                            entry.impls.push(location);
+                        }
+                        "Serialize" => {
+                            let entry = self.spawn_entry(&id, DataType::Unknown);
+                            entry.serializer = true;
+                            entry.impls.push(location);
+                        }
+                        "Protobuf" | "TryFrom" | "From" => {
+                            let (_, path, _) = i.trait_.as_ref().unwrap();
+                            let arg = match path.segments.last().map(op::generic_types) {
+                                Some(args) if !args.is_empty() => args[0],
+                                _ => continue,
+                            };
+                            let arg_name = op::type_to_string(arg);
+                            let self_name = op::type_to_string(i.self_ty.deref());
+                            let domain = match op::get_idents_from_types(arg).last() {
+                                Some(n) => format!("{}::{}", id_prefix, n),
+                                None => continue,
+                            };
+                            if impl_trait == "Protobuf" {
+                                let entry = self.spawn_entry(&id, DataType::Unknown);
+                                entry.protobuf.push(arg_name);
+                            } else if is_proto_path(&use_path(&arg_name, &imports)) {
+                                let entry = self.spawn_entry(&id, DataType::Unknown);
+                                entry.proto_from.push(arg_name);
+                            } else if is_proto_path(&use_path(&self_name, &imports)) {
+                                let entry = self.spawn_entry(&domain, DataType::Unknown);
+                                entry.proto_into.push(self_name);
+                            } else {
+                                self.conversions.push(Conversion {
+                                    id: id.clone(),
+                                    raw: arg_name,
+                                    from: true,
+                                });
+                                self.conversions.push(Conversion {
+                                    id: domain,
+                                    raw: self_name,
+                                    from: false,
+                                });
+                            }
                         }
-                        "Serialize" => self.spawn_entry(&id, DataType::Unknown).serializer = true,
                         _ => {}
                     }
                 }
@@ -253,13 +1105,14 @@ impl Collection {
         &self,
         collected_item_name: &str,
         collected_item_data: &Entry,
-    ) -> (Vec<String>, Vec<String>) {
-        let mut solid = Vec::<String>::new();
-        let mut dashed = Vec::<String>::new();
+    ) -> (Vec<Target>, Vec<Target>, Vec<String>) {
+        let mut unresolved = Vec::<String>::new();
+        let mut solid = Vec::<Target>::new();
+        let mut dashed = Vec::<Target>::new();
         let color = collected_item_data.get_color();
 
         'fields: for field_being_checked in &collected_item_data.fields {
-            for possible_object in self.0.keys() {
+            for possible_object in self.entries.keys() {
                 let (collected_item_name_slash, collected_item_name_mid, collected_item_name_last) =
                     path_parts(collected_item_name);
                 let (field_being_checked_slash, field_being_checked_mid, field_being_checked_last) =
@@ -284,7 +1137,13 @@ impl Collection {
                 // item: genesis::Genesis, field: validator::Info -> validator::Info
                 // item: abci/responses::EndBlock, field: validator::Update -> validator::Update
                 if possible_object == field_being_checked {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -304,7 +1163,13 @@ impl Collection {
                     && possible_object
                         == &joiner(&collected_item_name_prefix, &field_being_checked_last, "::")
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -328,7 +1193,13 @@ impl Collection {
                             "::",
                         )
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -347,7 +1218,13 @@ impl Collection {
                             "::",
                         )
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -365,7 +1242,13 @@ impl Collection {
                             "::",
                         )
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -374,10 +1257,15 @@ impl Collection {
                 // item: block::Block, field: transaction::Data -> abci/transaction::Data
                 // ...
                 if is_field_being_checked_relative
-                    && possible_object
-                        == &joiner(&"abci".to_string(), &field_being_checked_object, "/")
+                    && possible_object == &joiner("abci", &field_being_checked_object, "/")
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -389,7 +1277,7 @@ impl Collection {
                     && possible_object
                         == &joiner(
                             &joiner(
-                                &"abci".to_string(),
+                                "abci",
                                 &op::camelcase_to_snakecase(&field_being_checked_last),
                                 "/",
                             ),
@@ -397,7 +1285,13 @@ impl Collection {
                             "::",
                         )
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -407,7 +1301,13 @@ impl Collection {
                     && field_being_checked == "Channels"
                     && possible_object == "channel::Channels"
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -417,7 +1317,13 @@ impl Collection {
                     && field_being_checked == "PartSetHeader"
                     && possible_object == "block/parts::Header"
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -427,35 +1333,65 @@ impl Collection {
                     && field_being_checked == "super::Type"
                     && possible_object == "vote::Type"
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
                 // ChainId exception
                 // item: vote/canonical_vote::CanonicalVote, field: ChainId -> chain/id::Id
                 if field_being_checked == "ChainId" && possible_object == "chain/id::Id" {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
                 // Height exception
                 // item: proposal/canonical_proposal::CanonicalProposal, field: Height -> block/height::Height
                 if field_being_checked == "Height" && possible_object == "block/height::Height" {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
                 // Round exception
                 // item: proposal::Proposal, field: Round -> block/round::Round
                 if field_being_checked == "Round" && possible_object == "block/round::Round" {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
                 // BlockId exception
                 // item: proposal/canonical_proposal::CanonicalProposal, field: BlockId -> block/id::Id
                 if field_being_checked == "BlockId" && possible_object == "block/id::Id" {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -464,7 +1400,13 @@ impl Collection {
                 if field_being_checked == "SignedHeader"
                     && possible_object == "block/signed_header::SignedHeader"
                 {
-                    pusher(possible_object, &color, &mut solid, &mut dashed);
+                    pusher(
+                        possible_object,
+                        field_being_checked,
+                        &color,
+                        &mut solid,
+                        &mut dashed,
+                    );
                     continue 'fields;
                 }
 
@@ -527,24 +1469,246 @@ impl Collection {
                     continue 'fields;
                 }
             }
-            panic!(
-                "could not parse struct or enum: {}, field: {}",
-                collected_item_name, field_being_checked
-            );
+            unresolved.push(field_being_checked.clone());
         }
-        (solid, dashed)
+        (solid, dashed, unresolved)
     }
 
-    /// Parse collection into CSV data.
-    pub fn parse_to_csv(&self, only_json: bool, no_header: bool) -> String {
-        let mut result = String::new();
-        if !no_header {
-            result.push_str(consts::HEADER);
-            result.push_str("\n");
+    /// Resolve the dependencies of all public entries. Fields that can't be resolved are reported as diagnostics.
+    pub fn resolve(&mut self) {
+        self.resolve_conversions();
+        let mut edges = Vec::<Edge>::new();
+        let mut diagnostics = Vec::<Diagnostic>::new();
+        for (collected_item_name, collected_item_data) in &self.entries {
+            if let DataType::Unknown = collected_item_data.r#type {
+                diagnostics.push(Diagnostic {
+                    id: collected_item_name.clone(),
+                    field: None,
+                    message:
+                        "serialization implemented for a type that is not defined in the source"
+                            .to_string(),
+                });
+            }
+            if !collected_item_data.public {
+                continue;
+            }
+            let (solid, dashed, unresolved) =
+                self.build_dependencies_for_csv(collected_item_name, collected_item_data);
+            for (targets, is_solid) in &[(solid, true), (dashed, false)] {
+                for (to, ident) in targets {
+                    edges.push(Edge {
+                        from: collected_item_name.clone(),
+                        to: to.clone(),
+                        solid: *is_solid,
+                        fields: collected_item_data.members_with_ident(ident),
+                        label: None,
+                    });
+                }
+            }
+            for field in unresolved {
+                diagnostics.push(Diagnostic {
+                    id: collected_item_name.clone(),
+                    message: format!("could not resolve field type: {}", field),
+                    field: Some(field),
+                });
+            }
+        }
+        self.edges = edges;
+        self.diagnostics = diagnostics;
+    }
+
+    /// Record the conversions from and into the prost entries. The entry of the protobuf type is the only prost entry
+    /// with its name.
+    fn resolve_conversions(&mut self) {
+        for c in std::mem::take(&mut self.conversions) {
+            let name = c.raw.rsplit("::").next().unwrap_or(&c.raw);
+            let prost = self
+                .entries
+                .iter()
+                .filter(|(id, e)| e.prost && id.rsplit("::").next() == Some(name))
+                .count();
+            if prost != 1 || !self.entries.contains_key(&c.id) {
+                continue;
+            }
+            let entry = self.entries.get_mut(&c.id).unwrap();
+            if c.from {
+                entry.proto_from.push(c.raw);
+            } else {
+                entry.proto_into.push(c.raw);
+            }
+        }
+    }
+
+    /// Label the edges with the field names and multiplicities
+    pub fn label_edges(&mut self) {
+        for e in self.edges.iter_mut() {
+            let labels = match self.entries.get(&e.from) {
+                Some(entry) => entry.field_labels(&e.fields, &e.to),
+                None => continue,
+            };
+            if !labels.is_empty() {
+                e.label = Some(labels.join("<br>"));
+            }
+        }
+    }
+
+    /// Source of an entry: the source with the crate prefix of the id
+    fn source_of(&self, id: &str) -> Option<&Source> {
+        self.sources.iter().find(|s| match &s.prefix {
+            Some(prefix) => id.starts_with(&format!("{}::", prefix)),
+            None => true,
+        })
+    }
+
+    /// Link to the definition of an entry. `template` placeholders: `{rev}` (commit, or `HEAD` for the
+    /// working tree), `{path}` (path in the git repository), `{file}` (local path), `{line}` and `{column}`.
+    pub fn source_url(&self, id: &str, template: &str) -> Option<String> {
+        let location = self.entries.get(id)?.location.as_ref()?;
+        let source = self.source_of(id)?;
+        let folder = if source.path.ends_with(".rs") {
+            Path::new(&source.path)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+        } else {
+            Path::new(&source.path)
+        };
+        let file = folder.join(&location.file);
+        let file = file.canonicalize().unwrap_or(file);
+        // A relative path is not a valid file URL
+        if template.contains("{file}") && !file.is_absolute() {
+            return None;
+        }
+        Some(
+            template
+                .replace(
+                    "{rev}",
+                    source
+                        .commit
+                        .as_deref()
+                        .or(source.rev.as_deref())
+                        .unwrap_or("HEAD"),
+                )
+                .replace(
+                    "{path}",
+                    &format!(
+                        "{}{}",
+                        source.repo_path.as_deref().unwrap_or(""),
+                        location.file
+                    ),
+                )
+                .replace("{file}", file.to_str().unwrap_or(&location.file))
+                .replace("{line}", &location.line.to_string())
+                .replace("{column}", &location.column.to_string()),
+        )
+    }
+
+    /// Add a child node `Enum.Variant` for every enum variant with a payload. The enum is linked to its
+    /// variants and the links of the payload fields start from the variant nodes.
+    pub fn expand_variants(&mut self) {
+        let mut children = BTreeMap::<String, Entry>::new();
+        let mut edges = Vec::<Edge>::new();
+        for (id, entry) in &self.entries {
+            if entry.r#type != DataType::Enum || !entry.public {
+                continue;
+            }
+            for variant in entry.get_variants() {
+                if variant.kind == VariantKind::Unit {
+                    continue;
+                }
+                let child_id = format!("{}.{}", id, variant.name);
+                let mut child = Entry {
+                    r#type: DataType::Struct,
+                    fields: vec![],
+                    members: vec![],
+                    variants: vec![],
+                    // `rename_all` of an enum renames the variants, not the fields
+                    serde: ContainerAttrs::default(),
+                    location: variant.location.clone(),
+                    impls: vec![],
+                    doc: variant.doc.clone(),
+                    ..entry.clone()
+                };
+                for f in entry
+                    .members
+                    .iter()
+                    .filter(|f| f.variant() == Some(variant.name.as_str()))
+                {
+                    if let Ok(ty) = syn::parse_str::<syn::Type>(&f.ty) {
+                        child.add_to_fields(op::get_idents_from_types(&ty));
+                    }
+                    child.members.push(Field {
+                        name: f.ident().to_string(),
+                        ..f.clone()
+                    });
+                }
+                edges.push(Edge {
+                    from: id.clone(),
+                    to: child_id.clone(),
+                    solid: entry.get_color() != Color::White,
+                    fields: vec![],
+                    label: None,
+                });
+                children.insert(child_id, child);
+            }
+        }
+        for e in self.edges.drain(..) {
+            if !self
+                .entries
+                .get(&e.from)
+                .is_some_and(|entry| entry.r#type == DataType::Enum && entry.public)
+                || e.fields.is_empty()
+            {
+                edges.push(e);
+                continue;
+            }
+            // Split the link by the variants of its fields
+            let mut by_variant = BTreeMap::<String, Vec<String>>::new();
+            for field in &e.fields {
+                let from = match field.split_once('.') {
+                    Some((variant, _)) => format!("{}.{}", e.from, variant),
+                    None => e.from.clone(),
+                };
+                by_variant.entry(from).or_default().push(
+                    field
+                        .split_once('.')
+                        .map_or(field.as_str(), |(_, f)| f)
+                        .to_string(),
+                );
+            }
+            for (from, fields) in by_variant {
+                edges.push(Edge {
+                    from,
+                    to: e.to.clone(),
+                    solid: e.solid,
+                    fields,
+                    label: None,
+                });
+            }
         }
+        self.entries.extend(children);
+        self.edges = edges;
+    }
+
+    /// Parse collection into CSV data.
+    pub fn parse_to_csv(&self, template: &Template, only_json: bool, no_header: bool) -> String {
+        let mut rows = String::new();
+        let mut styles = Vec::<(Color, Encoding)>::new();
+        let mut types = Vec::<DataType>::new();
+        let mut stubs = String::new();
+        let mut labels = String::new();
         let only_public = true;
 
-        for (collected_item_name, collected_item_data) in &self.0 {
+        // Visible edges of the cycles get their own connector
+        let cyclic = Graph::new(self)
+            .cycles()
+            .into_iter()
+            .flat_map(|c| c.edges)
+            .map(|e| (e.from, e.to))
+            .collect::<BTreeSet<(String, String)>>();
+        let in_cycle =
+            |e: &Edge| (e.solid || !only_json) && cyclic.contains(&(e.from.clone(), e.to.clone()));
+
+        for (collected_item_name, collected_item_data) in &self.entries {
             if only_public && !collected_item_data.public {
                 continue;
             }
@@ -553,39 +1717,125 @@ impl Collection {
                 continue;
             }
 
-            let (solid, dashed) =
-                self.build_dependencies_for_csv(collected_item_name, collected_item_data);
+            // Targets of the refs, refs2, refs3 and cycle columns. Labeled edges go through a label node.
+            let mut links: [Vec<String>; 4] = Default::default();
+            for e in self.edges.iter().filter(|e| &e.from == collected_item_name) {
+                let column = if in_cycle(e) {
+                    3
+                } else if e.solid {
+                    0
+                } else if only_json {
+                    2
+                } else {
+                    1
+                };
+                match &e.label {
+                    Some(label) => {
+                        let mut row: [&str; 4] = Default::default();
+                        row[column] = &e.to;
+                        labels.push_str(
+                            format!(
+                                "{},rectangle,edge_label,{:?},{:?},{:?},{:?},\"\",\"\",{:?}\n",
+                                e.label_node(),
+                                row[0],
+                                row[1],
+                                row[2],
+                                row[3],
+                                label
+                            )
+                            .as_str(),
+                        );
+                        links[column].push(e.label_node());
+                    }
+                    None => links[column].push(e.to.clone()),
+                }
+            }
+            let [solid, dashed, hidden, cycle] = links;
+
+            // Collapsed neighbors are connected with a dashed line in both modes
+            let mut collapsed = Vec::<String>::new();
+            for stub in self.stubs.iter().filter(|s| &s.id == collected_item_name) {
+                let into = if stub.upstream {
+                    collected_item_name.clone()
+                } else {
+                    collapsed.push(stub.name());
+                    "".to_string()
+                };
+                stubs.push_str(
+                    format!(
+                        "{},rectangle,stub,\"\",{:?},\"\",\"\",\"\",\"\",\"\"\n",
+                        stub.name(),
+                        into
+                    )
+                    .as_str(),
+                );
+            }
 
-            let deps = collected_item_data.fields.join("<br>");
+            let deps = if template.class_labels {
+                collected_item_data.class_lines().join("<br>")
+            } else {
+                collected_item_data.fields.join("<br>")
+            };
 
-            result.push_str(
+            let style = (
+                collected_item_data.get_color(),
+                collected_item_data.get_encoding(),
+            );
+            styles.push(style);
+            types.push(collected_item_data.r#type);
+            rows.push_str(
                 format!(
-                    "{},{},{},{:?},{:?},{:?},{:?}\n",
+                    "{},{},{}{},{:?},{:?},{:?},{:?},{:?},{:?},{:?}\n",
                     collected_item_name,
-                    collected_item_data.r#type.to_string(),
-                    collected_item_data.get_color().to_string(),
+                    collected_item_data.r#type,
+                    style.0,
+                    style.1.suffix(),
                     solid.join(","),
                     //refs2 - dashed
-                    if only_json {
-                        "".to_string()
-                    } else {
-                        dashed.join(",")
-                    },
+                    [dashed, collapsed].concat().join(","),
                     //refs3 - invisible
-                    if only_json {
-                        dashed.join(",")
-                    } else {
-                        "".to_string()
-                    },
+                    hidden.join(","),
+                    cycle.join(","),
+                    self.source_url(collected_item_name, &template.source_url)
+                        .unwrap_or_default(),
+                    collected_item_data.tooltip(),
                     deps,
                 )
                 .as_str(),
             );
         }
+
+        let mut result = String::new();
+        if !no_header {
+            result.push_str(
+                template
+                    .render(
+                        &styles,
+                        &types,
+                        &Extras {
+                            stubs: !stubs.is_empty(),
+                            cycles: !cyclic.is_empty(),
+                            labels: !labels.is_empty(),
+                        },
+                    )
+                    .as_str(),
+            );
+            result.push('\n');
+        }
+        result.push_str(rows.as_str());
+        result.push_str(labels.as_str());
+        result.push_str(stubs.as_str());
         result
     }
 }
 
+/// Escape the text of an HTML label
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
 fn joiner(a: &str, b: &str, sep: &str) -> String {
     if a.is_empty() {
         b.to_string()
@@ -617,10 +1867,17 @@ fn path_parts(path: &str) -> (String, String, String) {
     (slash_vec.join("/"), colon2.join("::"), last.to_string())
 }
 
-fn pusher(possible_object: &str, color: &Color, solid: &mut Vec<String>, dashed: &mut Vec<String>) {
+fn pusher(
+    possible_object: &str,
+    field_being_checked: &str,
+    color: &Color,
+    solid: &mut Vec<Target>,
+    dashed: &mut Vec<Target>,
+) {
+    let resolved = (possible_object.to_string(), field_being_checked.to_string());
     if color == &Color::Green || color == &Color::GreenGradient {
-        solid.push(possible_object.to_string());
+        solid.push(resolved);
     } else {
-        dashed.push(possible_object.to_string());
+        dashed.push(resolved);
     }
 }
@@ -0,0 +1,329 @@
+use crate::core::db::{Collection, Color, Edge, Entry, Field};
+use crate::core::template::Template;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Changes of an entry that exists on both sides
+#[derive(Debug, Default)]
+pub struct Change {
+    pub added_fields: Vec<Field>,
+    pub removed_fields: Vec<Field>,
+    /// Same field name with a different type: (old, new)
+    pub changed_fields: Vec<(Field, Field)>,
+    /// Old and new color, if the serialization changed
+    pub color: Option<(Color, Color)>,
+}
+
+impl Change {
+    fn is_empty(&self) -> bool {
+        self.added_fields.is_empty()
+            && self.removed_fields.is_empty()
+            && self.changed_fields.is_empty()
+            && self.color.is_none()
+    }
+}
+
+/// Structural difference between two analyses. Only public entries are compared.
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// (old id, new id)
+    pub renamed: Vec<(String, String)>,
+    /// Changes keyed by the new id
+    pub changed: BTreeMap<String, Change>,
+    pub added_edges: Vec<Edge>,
+    pub removed_edges: Vec<Edge>,
+    /// Edges that exist on both sides. Renamed ids are mapped to the new id in all edge lists.
+    pub kept_edges: Vec<Edge>,
+}
+
+fn public_ids(collection: &Collection) -> BTreeSet<String> {
+    collection
+        .entries()
+        .filter(|(_, e)| e.public)
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+fn type_name(id: &str) -> &str {
+    id.rsplit("::").next().unwrap_or(id)
+}
+
+/// Match the public entries of two collections by id.
+/// Returns the added ids, the removed ids and the renamed (or moved) (old id, new id) pairs.
+pub fn match_entries(
+    old: &Collection,
+    new: &Collection,
+) -> (Vec<String>, Vec<String>, Vec<(String, String)>) {
+    let old_ids = public_ids(old);
+    let new_ids = public_ids(new);
+    let mut removed = old_ids
+        .difference(&new_ids)
+        .cloned()
+        .collect::<Vec<String>>();
+    let mut added = new_ids
+        .difference(&old_ids)
+        .cloned()
+        .collect::<Vec<String>>();
+
+    // Renamed or moved entries: same type name in a different module first,
+    // then the same data type with the same fields under a different name.
+    let mut renamed = Vec::<(String, String)>::new();
+    for pass in 0..2 {
+        for old_id in removed.clone() {
+            let old_entry = old.get(&old_id).unwrap();
+            let found = added.iter().position(|new_id| {
+                let new_entry = new.get(new_id).unwrap();
+                if pass == 0 {
+                    type_name(&old_id) == type_name(new_id)
+                } else {
+                    !old_entry.members.is_empty()
+                        && old_entry.r#type == new_entry.r#type
+                        && old_entry.members == new_entry.members
+                }
+            });
+            if let Some(i) = found {
+                let new_id = added.remove(i);
+                removed.retain(|r| r != &old_id);
+                renamed.push((old_id, new_id));
+            }
+        }
+    }
+    renamed.sort();
+    (added, removed, renamed)
+}
+
+fn compare_entries(old: &Entry, new: &Entry) -> Change {
+    let mut change = Change::default();
+    for f in &new.members {
+        match old.members.iter().find(|o| o.name == f.name) {
+            None => change.added_fields.push(f.clone()),
+            Some(o) if o.ty != f.ty => change.changed_fields.push((o.clone(), f.clone())),
+            Some(_) => {}
+        }
+    }
+    for o in &old.members {
+        if !new.members.iter().any(|f| f.name == o.name) {
+            change.removed_fields.push(o.clone());
+        }
+    }
+    let (old_color, new_color) = (old.get_color(), new.get_color());
+    if old_color != new_color {
+        change.color = Some((old_color, new_color));
+    }
+    change
+}
+
+impl Diff {
+    /// Compare two collections.
+    pub fn new(old: &Collection, new: &Collection) -> Self {
+        let (added, removed, renamed) = match_entries(old, new);
+        let old_ids = public_ids(old);
+        let new_ids = public_ids(new);
+
+        let mut changed = BTreeMap::<String, Change>::new();
+        let common = old_ids
+            .intersection(&new_ids)
+            .map(|id| (id.clone(), id.clone()))
+            .chain(renamed.iter().cloned());
+        for (old_id, new_id) in common {
+            let change = compare_entries(old.get(&old_id).unwrap(), new.get(&new_id).unwrap());
+            if !change.is_empty() {
+                changed.insert(new_id, change);
+            }
+        }
+
+        let rename = |id: &String| {
+            renamed
+                .iter()
+                .find(|(o, _)| o == id)
+                .map(|(_, n)| n.clone())
+                .unwrap_or_else(|| id.clone())
+        };
+        let old_edges = old
+            .edges()
+            .iter()
+            .map(|e| (rename(&e.from), rename(&e.to), e))
+            .collect::<Vec<(String, String, &Edge)>>();
+        let new_edges = new.edges();
+        let mut added_edges = Vec::<Edge>::new();
+        let mut kept_edges = Vec::<Edge>::new();
+        for e in new_edges.iter() {
+            if old_edges.iter().any(|(f, t, _)| f == &e.from && t == &e.to) {
+                kept_edges.push(e.clone());
+            } else {
+                added_edges.push(e.clone());
+            }
+        }
+        let removed_edges = old_edges
+            .into_iter()
+            .filter(|(f, t, _)| !new_edges.iter().any(|e| &e.from == f && &e.to == t))
+            .map(|(from, to, e)| Edge {
+                from,
+                to,
+                ..e.clone()
+            })
+            .collect();
+
+        Self {
+            added,
+            removed,
+            renamed,
+            changed,
+            added_edges,
+            removed_edges,
+            kept_edges,
+        }
+    }
+
+    /// Human readable report
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::<String>::new();
+        let mut section = |title: &str, items: Vec<String>| {
+            if !items.is_empty() {
+                lines.push(format!("{}:", title));
+                lines.extend(items.into_iter().map(|i| format!("  {}", i)));
+            }
+        };
+        section(
+            "Added types",
+            self.added.iter().map(|id| format!("+ {}", id)).collect(),
+        );
+        section(
+            "Removed types",
+            self.removed.iter().map(|id| format!("- {}", id)).collect(),
+        );
+        section(
+            "Renamed types",
+            self.renamed
+                .iter()
+                .map(|(o, n)| format!("~ {} -> {}", o, n))
+                .collect(),
+        );
+        section(
+            "Changed types",
+            self.changed
+                .iter()
+                .map(|(id, change)| {
+                    let mut details = vec![id.clone()];
+                    details.extend(
+                        change_lines(change)
+                            .into_iter()
+                            .map(|l| format!("    {}", l)),
+                    );
+                    details.join("\n  ")
+                })
+                .collect(),
+        );
+        section(
+            "Added edges",
+            self.added_edges
+                .iter()
+                .map(|e| format!("+ {} -> {}", e.from, e.to))
+                .collect(),
+        );
+        section(
+            "Removed edges",
+            self.removed_edges
+                .iter()
+                .map(|e| format!("- {} -> {}", e.from, e.to))
+                .collect(),
+        );
+        if lines.is_empty() {
+            "No changes".to_string()
+        } else {
+            lines.join("\n")
+        }
+    }
+
+    /// draw.io CSV diagram of the union of both sides. Additions are green, removals are red.
+    /// With `only_json`, entries without serialization on both sides are left out.
+    pub fn to_csv(
+        &self,
+        old: &Collection,
+        new: &Collection,
+        template: &Template,
+        only_json: bool,
+        no_header: bool,
+    ) -> String {
+        let mut result = String::new();
+        if !no_header {
+            result.push_str(template.render_diff().as_str());
+            result.push('\n');
+        }
+
+        let mut nodes = BTreeMap::<String, (&Entry, &str, Vec<String>)>::new();
+        for (id, entry) in new.entries().filter(|(_, e)| e.public) {
+            let (color, notes) = if self.added.contains(id) {
+                ("added", vec!["added".to_string()])
+            } else if let Some(change) = self.changed.get(id) {
+                ("changed", change_lines(change))
+            } else {
+                ("unchanged", vec![])
+            };
+            nodes.insert(id.clone(), (entry, color, notes));
+        }
+        for (old_id, new_id) in &self.renamed {
+            if let Some((_, color, notes)) = nodes.get_mut(new_id) {
+                *color = "changed";
+                notes.insert(0, format!("renamed from {}", old_id));
+            }
+        }
+        for id in &self.removed {
+            nodes.insert(
+                id.clone(),
+                (old.get(id).unwrap(), "removed", vec!["removed".to_string()]),
+            );
+        }
+
+        if only_json {
+            nodes.retain(|id, (entry, _, _)| {
+                entry.get_color() != Color::White
+                    || self.changed.get(id).is_some_and(|c| c.color.is_some())
+            });
+        }
+
+        for (id, (entry, color, notes)) in &nodes {
+            let refs = |edges: &Vec<Edge>| {
+                edges
+                    .iter()
+                    .filter(|e| &e.from == id)
+                    .map(|e| e.to.clone())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            };
+            result.push_str(
+                format!(
+                    "{},{},{},{:?},{:?},{:?},{:?}\n",
+                    id,
+                    entry.r#type,
+                    color,
+                    refs(&self.kept_edges),
+                    refs(&self.added_edges),
+                    refs(&self.removed_edges),
+                    notes.join("<br>"),
+                )
+                .as_str(),
+            );
+        }
+        result
+    }
+}
+
+fn change_lines(change: &Change) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    for f in &change.added_fields {
+        lines.push(format!("+ {}: {}", f.name, f.ty));
+    }
+    for f in &change.removed_fields {
+        lines.push(format!("- {}: {}", f.name, f.ty));
+    }
+    for (o, n) in &change.changed_fields {
+        lines.push(format!("~ {}: {} -> {}", n.name, o.ty, n.ty));
+    }
+    if let Some((o, n)) = &change.color {
+        lines.push(format!("color: {} -> {}", o, n));
+    }
+    lines
+}
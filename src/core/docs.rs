@@ -0,0 +1,310 @@
+use crate::core::db::{escape_html, Collection, Color, DataType, Entry, Field, VariantKind};
+use crate::core::group::module_of;
+use crate::core::template::Template;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Documented types and fields of a module
+#[derive(Debug, Default, Serialize)]
+pub struct Coverage {
+    pub module: String,
+    pub types: usize,
+    pub documented_types: usize,
+    /// Struct fields, enum variants and the named fields of struct variants
+    pub fields: usize,
+    pub documented_fields: usize,
+}
+
+impl Coverage {
+    /// Share of the documented types and fields, 1 if there is nothing to document
+    pub fn ratio(&self) -> f64 {
+        let total = self.types + self.fields;
+        if total == 0 {
+            1.0
+        } else {
+            (self.documented_types + self.documented_fields) as f64 / total as f64
+        }
+    }
+
+    fn add(&mut self, entry: &Entry) {
+        self.types += 1;
+        self.documented_types += entry.doc().is_some() as usize;
+        for doc in documented(entry) {
+            self.fields += 1;
+            self.documented_fields += doc as usize;
+        }
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.module.clone(),
+            format!("{}/{}", self.documented_types, self.types),
+            format!("{}/{}", self.documented_fields, self.fields),
+            format!("{:.0}%", self.ratio() * 100.0),
+        ]
+    }
+}
+
+/// Whether the fields and variants of an entry are documented
+fn documented(entry: &Entry) -> Vec<bool> {
+    match entry.r#type {
+        DataType::Enum => {
+            let mut result = entry
+                .get_variants()
+                .iter()
+                .map(|v| v.doc.is_some())
+                .collect::<Vec<bool>>();
+            for f in &entry.members {
+                if f.variant().is_some() && f.ident().parse::<usize>().is_err() {
+                    result.push(f.doc.is_some());
+                }
+            }
+            result
+        }
+        _ => entry.members.iter().map(|f| f.doc.is_some()).collect(),
+    }
+}
+
+/// Doc coverage of the public types by module
+#[derive(Debug, Serialize)]
+pub struct DocCoverage {
+    pub modules: Vec<Coverage>,
+    pub total: Coverage,
+}
+
+impl DocCoverage {
+    /// With `only_json`, only the serializable types are counted.
+    pub fn new(collection: &Collection, only_json: bool) -> Self {
+        let mut modules = BTreeMap::<&str, Coverage>::new();
+        let mut total = Coverage {
+            module: "total".to_string(),
+            ..Coverage::default()
+        };
+        for (id, entry) in documentable(collection, only_json) {
+            let module = module_of(id);
+            modules
+                .entry(module)
+                .or_insert_with(|| Coverage {
+                    module: module.to_string(),
+                    ..Coverage::default()
+                })
+                .add(entry);
+            total.add(entry);
+        }
+        Self {
+            modules: modules.into_values().collect(),
+            total,
+        }
+    }
+
+    /// Aligned table with the total in the last row
+    pub fn to_text(&self) -> String {
+        let mut rows = vec![["module", "types", "fields", "coverage"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>()];
+        rows.extend(self.modules.iter().map(Coverage::row));
+        rows.push(self.total.row());
+        let mut widths = vec![0; rows[0].len()];
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.len());
+            }
+        }
+        rows.iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(i, cell)| match i {
+                        0 => format!("{:<width$}", cell, width = widths[i]),
+                        _ => format!("{:>width$}", cell, width = widths[i]),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("  ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Comma-separated table, for spreadsheets
+    pub fn to_csv(&self) -> String {
+        let mut lines =
+            vec!["module,types,documented_types,fields,documented_fields,coverage".to_string()];
+        for c in self.modules.iter().chain(Some(&self.total)) {
+            lines.push(format!(
+                "{},{},{},{},{},{:.3}",
+                c.module,
+                c.types,
+                c.documented_types,
+                c.fields,
+                c.documented_fields,
+                c.ratio()
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// The public structs and enums
+fn documentable(
+    collection: &Collection,
+    only_json: bool,
+) -> impl Iterator<Item = (&String, &Entry)> {
+    collection.entries().filter(move |(_, e)| {
+        e.public && e.r#type != DataType::Unknown && !(only_json && e.get_color() == Color::White)
+    })
+}
+
+/// Markdown paragraphs as HTML paragraphs
+fn paragraphs(doc: &str) -> String {
+    doc.split("\n\n")
+        .map(|p| format!("<p>{}</p>", escape_html(p)))
+        .collect()
+}
+
+/// Table row of a field: name, type, JSON key and doc
+fn field_row(entry: &Entry, f: &Field, name: &str) -> String {
+    format!(
+        "<tr><td><code>{}</code></td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>\n",
+        name,
+        escape_html(&f.ty),
+        f.json_key(&entry.serde, true)
+            .map_or("<i>skipped</i>".to_string(), |k| format!(
+                "<code>{}</code>",
+                escape_html(&k)
+            )),
+        f.doc.as_deref().map(paragraphs).unwrap_or_default()
+    )
+}
+
+/// Documentation page of the public types: the modules with their doc coverage in a side panel, the types with
+/// their docs, fields, variants and source links in the main panel.
+pub fn to_html(collection: &Collection, template: &Template, only_json: bool) -> String {
+    let coverage = DocCoverage::new(collection, only_json);
+    let mut nav = String::new();
+    let mut main = String::new();
+    let mut module = None;
+    for (id, entry) in documentable(collection, only_json) {
+        if module != Some(module_of(id)) {
+            if module.is_some() {
+                nav.push_str("</ul>\n");
+            }
+            module = Some(module_of(id));
+            let ratio = coverage
+                .modules
+                .iter()
+                .find(|c| Some(c.module.as_str()) == module)
+                .map_or(1.0, Coverage::ratio);
+            nav.push_str(
+                format!(
+                    "<h3>{} <small>{:.0}%</small></h3>\n<ul>\n",
+                    module_of(id),
+                    ratio * 100.0
+                )
+                .as_str(),
+            );
+        }
+        let name = id.rsplit("::").next().unwrap_or(id);
+        nav.push_str(format!("<li><a href=\"#{}\">{}</a></li>\n", id, name).as_str());
+
+        main.push_str(format!("<section id=\"{}\">\n<h2>{}</h2>\n", id, id).as_str());
+        let mut meta = vec![
+            format!("{:?}", entry.r#type).to_lowercase(),
+            entry.get_color().to_string(),
+        ];
+        if let Some(location) = entry.location() {
+            meta.push(format!(
+                "<a href=\"{}\">{}</a>",
+                collection
+                    .source_url(id, &template.source_url)
+                    .unwrap_or_default(),
+                location
+            ));
+        }
+        main.push_str(format!("<p class=\"meta\">{}</p>\n", meta.join(" · ")).as_str());
+        main.push_str(
+            entry
+                .doc()
+                .map_or(
+                    "<p class=\"missing\">Not documented.</p>".to_string(),
+                    paragraphs,
+                )
+                .as_str(),
+        );
+        main.push_str("\n<table>\n<tr><th>Field</th><th>Type</th><th>JSON</th><th>Doc</th></tr>\n");
+        if entry.r#type == DataType::Enum {
+            for variant in entry.get_variants() {
+                main.push_str(
+                    format!(
+                        "<tr class=\"variant\"><td><code>{}</code></td><td>{:?}</td><td>{}</td><td>{}</td></tr>\n",
+                        variant.name,
+                        variant.kind,
+                        variant
+                            .json_name(&entry.serde, true)
+                            .map_or("<i>skipped</i>".to_string(), |n| format!(
+                                "<code>{}</code>",
+                                escape_html(&n)
+                            )),
+                        variant.doc.as_deref().map(paragraphs).unwrap_or_default()
+                    )
+                    .as_str(),
+                );
+                if variant.kind == VariantKind::Unit {
+                    continue;
+                }
+                for f in entry
+                    .members
+                    .iter()
+                    .filter(|f| f.variant() == Some(variant.name.as_str()))
+                {
+                    main.push_str(
+                        field_row(entry, f, &format!("&nbsp;&nbsp;{}", f.ident())).as_str(),
+                    );
+                }
+            }
+        } else {
+            for f in &entry.members {
+                main.push_str(field_row(entry, f, &f.name).as_str());
+            }
+        }
+        main.push_str("</table>\n</section>\n");
+    }
+    if module.is_some() {
+        nav.push_str("</ul>\n");
+    }
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; font-family: sans-serif; display: flex; }}
+nav {{ position: sticky; top: 0; height: 100vh; overflow-y: auto; width: 20em; padding: 1em; background: #f5f5f5; box-sizing: border-box; }}
+nav ul {{ list-style: none; padding-left: 1em; }}
+main {{ flex: 1; padding: 1em 2em; }}
+section {{ border-bottom: 1px solid #ddd; padding-bottom: 1em; }}
+table {{ border-collapse: collapse; }}
+td, th {{ border: 1px solid #ddd; padding: 0.2em 0.5em; text-align: left; vertical-align: top; }}
+td p {{ margin: 0; }}
+tr.variant {{ background: #fafafa; }}
+.meta {{ color: gray; }}
+.missing {{ color: #b85450; }}
+</style>
+</head>
+<body>
+<nav>
+<h2>{title}</h2>
+<p>Documented: {coverage:.0}%</p>
+{nav}</nav>
+<main>
+{main}</main>
+</body>
+</html>"#,
+        title = escape_html(&template.title),
+        coverage = coverage.total.ratio() * 100.0,
+        nav = nav,
+        main = main,
+    )
+}
@@ -0,0 +1,446 @@
+use crate::core::db::Collection;
+use crate::core::graph::Graph;
+use crate::core::schema::Generator;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A fixture file (or a folder of `.json` files) and the type it deserializes to
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    /// Relative to the map file
+    pub path: String,
+    /// Type name, for example `block::Block`
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// JSON pointer of the value in the file, for example `/result/block` in an RPC response
+    #[serde(default)]
+    pub pointer: String,
+}
+
+/// Fixture map file: `[[fixture]]` tables
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureMap {
+    #[serde(rename = "fixture", default)]
+    pub fixtures: Vec<Fixture>,
+    #[serde(skip)]
+    folder: PathBuf,
+}
+
+impl FixtureMap {
+    pub fn from_file(path: &Path) -> Self {
+        let src = fs::read_to_string(path).expect("Unable to read fixture map");
+        let mut map: FixtureMap = toml::from_str(&src).expect("Unable to parse fixture map");
+        map.folder = path.parent().map_or(PathBuf::new(), Path::to_path_buf);
+        map
+    }
+
+    /// The files of a fixture, sorted
+    pub fn files(&self, fixture: &Fixture) -> Vec<PathBuf> {
+        let path = self.folder.join(&fixture.path);
+        if !path.is_dir() {
+            return vec![path];
+        }
+        let mut files = WalkDir::new(&path)
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(|e| e.into_path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect::<Vec<PathBuf>>();
+        files.sort();
+        files
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProblemKind {
+    /// Key rejected by `deny_unknown_fields`
+    UnknownKey,
+    /// Key that `Deserialize` ignores
+    IgnoredKey,
+    /// Required field that is not in the JSON
+    Missing,
+    /// Value of the wrong type or with the wrong tag
+    Mismatch,
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ProblemKind::UnknownKey => "unknown key",
+            ProblemKind::IgnoredKey => "ignored key",
+            ProblemKind::Missing => "missing",
+            ProblemKind::Mismatch => "mismatch",
+        })
+    }
+}
+
+/// A difference between a fixture and the type shape, at a JSON path
+#[derive(Debug)]
+pub struct Problem {
+    pub path: String,
+    pub kind: ProblemKind,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.path, self.kind, self.message)
+    }
+}
+
+/// Properties of an object schema, with the properties of the flattened types
+#[derive(Debug, Default)]
+struct Shape {
+    properties: Map<String, Value>,
+    required: Vec<String>,
+    /// `false` for `deny_unknown_fields`, the value schema of a flattened map, `None` if unknown keys are ignored
+    additional: Option<Value>,
+}
+
+/// Checks JSON values against the JSON accepted by `Deserialize`
+pub struct Checker {
+    defs: Map<String, Value>,
+}
+
+impl Checker {
+    /// Checker of the given types and the types they use
+    pub fn new(collection: &Collection, roots: &[String]) -> Self {
+        let document = Generator::new(collection, false).document(roots);
+        Self {
+            defs: match &document["$defs"] {
+                Value::Object(defs) => defs.clone(),
+                _ => Map::new(),
+            },
+        }
+    }
+
+    /// Problems of a value of the entry `id`
+    pub fn check(&self, id: &str, value: &Value) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        if let Some(schema) = self.defs.get(id) {
+            self.check_value(schema, value, "$", &mut problems);
+        }
+        problems
+    }
+
+    /// Follow the `$ref`s
+    fn resolve<'a>(&'a self, mut schema: &'a Value) -> &'a Value {
+        while let Some(Value::String(reference)) = schema.get("$ref") {
+            let id = reference
+                .trim_start_matches("#/$defs/")
+                .replace("~1", "/")
+                .replace("~0", "~");
+            match self.defs.get(&id) {
+                Some(def) => schema = def,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    fn check_value(&self, schema: &Value, value: &Value, path: &str, problems: &mut Vec<Problem>) {
+        let schema = self.resolve(schema);
+        if let Some(Value::Array(branches)) = schema.get("allOf") {
+            match self.shape(schema) {
+                Some(shape) => self.check_object(&shape, value, path, problems),
+                None => {
+                    for branch in branches {
+                        self.check_value(branch, value, path, problems);
+                    }
+                }
+            }
+            return;
+        }
+        if let Some(Value::Array(branches)) = schema.get("oneOf").or_else(|| schema.get("anyOf")) {
+            self.check_union(branches, value, path, problems);
+            return;
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                problems.push(mismatch(path, &expected.to_string(), value));
+            }
+            return;
+        }
+        match schema.get("type").and_then(Value::as_str) {
+            Some("object") => match self.shape(schema) {
+                Some(shape) => self.check_object(&shape, value, path, problems),
+                None => problems.push(mismatch(path, "object", value)),
+            },
+            Some("array") => self.check_array(schema, value, path, problems),
+            Some(json) if !has_type(json, value) => problems.push(mismatch(path, json, value)),
+            // Custom implementations and unresolved types accept anything
+            _ => {}
+        }
+    }
+
+    fn check_array(&self, schema: &Value, value: &Value, path: &str, problems: &mut Vec<Problem>) {
+        let items = match value {
+            Value::Array(items) => items,
+            _ => return problems.push(mismatch(path, "array", value)),
+        };
+        if let Some(Value::Array(prefix)) = schema.get("prefixItems") {
+            if prefix.len() != items.len() {
+                problems.push(Problem {
+                    path: path.to_string(),
+                    kind: ProblemKind::Mismatch,
+                    message: format!("expected {} items, found {}", prefix.len(), items.len()),
+                });
+            }
+            for (i, (schema, item)) in prefix.iter().zip(items).enumerate() {
+                self.check_value(schema, item, &format!("{}[{}]", path, i), problems);
+            }
+        } else if let Some(schema) = schema.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                self.check_value(schema, item, &format!("{}[{}]", path, i), problems);
+            }
+        }
+    }
+
+    fn check_object(&self, shape: &Shape, value: &Value, path: &str, problems: &mut Vec<Problem>) {
+        let object = match value {
+            Value::Object(object) => object,
+            _ => return problems.push(mismatch(path, "object", value)),
+        };
+        for key in &shape.required {
+            if !object.contains_key(key) {
+                problems.push(Problem {
+                    path: path.to_string(),
+                    kind: ProblemKind::Missing,
+                    message: format!("required field {:?}", key),
+                });
+            }
+        }
+        for (key, item) in object {
+            let item_path = member(path, key);
+            match (shape.properties.get(key), &shape.additional) {
+                (Some(schema), _) => self.check_value(schema, item, &item_path, problems),
+                (None, Some(Value::Bool(false))) => problems.push(Problem {
+                    path: item_path,
+                    kind: ProblemKind::UnknownKey,
+                    message: "rejected by deny_unknown_fields".to_string(),
+                }),
+                (None, Some(schema)) => self.check_value(schema, item, &item_path, problems),
+                (None, None) => problems.push(Problem {
+                    path: item_path,
+                    kind: ProblemKind::IgnoredKey,
+                    message: "not a field of the type".to_string(),
+                }),
+            }
+        }
+    }
+
+    /// The value matches one of the variants, or the problems of the closest variant are reported: the variants with
+    /// the same tag first, then the fewest problems.
+    fn check_union(
+        &self,
+        branches: &[Value],
+        value: &Value,
+        path: &str,
+        problems: &mut Vec<Problem>,
+    ) {
+        let mut closest: Option<(bool, Vec<Problem>)> = None;
+        for branch in branches {
+            let mut found = Vec::new();
+            self.check_value(branch, value, path, &mut found);
+            if found.is_empty() {
+                return;
+            }
+            let tagged = self.same_tag(branch, value);
+            if closest
+                .as_ref()
+                .is_none_or(|(t, c)| (tagged, c.len()) > (*t, found.len()))
+            {
+                closest = Some((tagged, found));
+            }
+        }
+        problems.extend(closest.map(|(_, c)| c).unwrap_or_default());
+    }
+
+    /// Whether an object has the tag of an enum variant: the value of the `tag` field, or the key of an externally
+    /// tagged variant
+    fn same_tag(&self, branch: &Value, value: &Value) -> bool {
+        let (shape, object) = match (self.shape(branch), value) {
+            (Some(shape), Value::Object(object)) => (shape, object),
+            _ => return false,
+        };
+        let tags = shape
+            .properties
+            .iter()
+            .filter_map(|(key, schema)| schema.get("const").map(|tag| (key, tag)))
+            .collect::<Vec<(&String, &Value)>>();
+        if !tags.is_empty() {
+            return tags.iter().all(|(key, tag)| object.get(*key) == Some(tag));
+        }
+        match (shape.required.as_slice(), &shape.additional) {
+            ([key], Some(Value::Bool(false))) => object.len() == 1 && object.contains_key(key),
+            _ => false,
+        }
+    }
+
+    /// Object shape of a schema, merged with the flattened types. `None` for other schemas.
+    fn shape(&self, schema: &Value) -> Option<Shape> {
+        let schema = self.resolve(schema);
+        if let Some(Value::Array(branches)) = schema.get("allOf") {
+            let mut merged = Shape::default();
+            for branch in branches {
+                let shape = self.shape(branch)?;
+                merged.properties.extend(shape.properties);
+                merged.required.extend(shape.required);
+                if merged.additional.is_none()
+                    || shape.additional.as_ref().is_some_and(Value::is_object)
+                {
+                    merged.additional = shape.additional;
+                }
+            }
+            // A flattened struct doesn't reject the keys of the other flattened types
+            if merged.additional == Some(Value::Bool(false)) && branches.len() > 1 {
+                merged.additional = None;
+            }
+            return Some(merged);
+        }
+        if schema.get("type").and_then(Value::as_str) != Some("object") {
+            return None;
+        }
+        Some(Shape {
+            properties: match schema.get("properties") {
+                Some(Value::Object(properties)) => properties.clone(),
+                _ => Map::new(),
+            },
+            required: schema
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|r| {
+                    r.iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            additional: schema.get("additionalProperties").cloned(),
+        })
+    }
+}
+
+/// Whether a value has a JSON Schema primitive type
+fn has_type(json: &str, value: &Value) -> bool {
+    match json {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        _ => true,
+    }
+}
+
+fn mismatch(path: &str, expected: &str, value: &Value) -> Problem {
+    let found = match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(n) => format!("number {}", n),
+        Value::String(s) if s.len() > 40 => "string".to_string(),
+        Value::String(s) => format!("string {:?}", s),
+        Value::Array(_) => "array".to_string(),
+        Value::Object(_) => "object".to_string(),
+    };
+    Problem {
+        path: path.to_string(),
+        kind: ProblemKind::Mismatch,
+        message: format!("expected {}, found {}", expected, found),
+    }
+}
+
+/// JSON path of an object member: `$.key`, or `$["key"]` if the key is not an identifier
+fn member(path: &str, key: &str) -> String {
+    let mut chars = key.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        format!("{}.{}", path, key)
+    } else {
+        format!("{}[{:?}]", path, key)
+    }
+}
+
+/// Problems of the fixture files, by file (and JSON pointer)
+#[derive(Debug)]
+pub struct FixtureReport {
+    pub files: BTreeMap<String, Vec<Problem>>,
+}
+
+impl FixtureReport {
+    /// Check every fixture file of the map. Fails on the type names that can't be resolved.
+    pub fn new(collection: &Collection, map: &FixtureMap) -> Result<Self, String> {
+        let graph = Graph::new(collection);
+        let ids = map
+            .fixtures
+            .iter()
+            .map(|f| graph.find(&f.type_name))
+            .collect::<Result<Vec<String>, String>>()?;
+        let checker = Checker::new(collection, &ids);
+        let mut files = BTreeMap::new();
+        for (fixture, id) in map.fixtures.iter().zip(&ids) {
+            for file in map.files(fixture) {
+                let src = fs::read_to_string(&file)
+                    .unwrap_or_else(|e| panic!("Unable to read {}: {}", file.display(), e));
+                let problems = match serde_json::from_str::<Value>(&src) {
+                    Ok(json) => match json.pointer(&fixture.pointer) {
+                        Some(value) => checker.check(id, value),
+                        None => vec![Problem {
+                            path: "$".to_string(),
+                            kind: ProblemKind::Missing,
+                            message: format!("no value at {}", fixture.pointer),
+                        }],
+                    },
+                    Err(e) => vec![Problem {
+                        path: "$".to_string(),
+                        kind: ProblemKind::Mismatch,
+                        message: format!("invalid JSON: {}", e),
+                    }],
+                };
+                let mut name = file.display().to_string();
+                if !fixture.pointer.is_empty() {
+                    name.push('#');
+                    name.push_str(&fixture.pointer);
+                }
+                files.insert(name, problems);
+            }
+        }
+        Ok(Self { files })
+    }
+
+    /// Whether a fixture would not deserialize. Ignored keys are only reported.
+    pub fn failed(&self) -> bool {
+        self.files
+            .values()
+            .flatten()
+            .any(|p| p.kind != ProblemKind::IgnoredKey)
+    }
+
+    /// One line per problem, prefixed with the file, and a summary
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        for (file, problems) in &self.files {
+            for problem in problems {
+                lines.push(format!("{}: {}", file, problem));
+            }
+        }
+        lines.push(format!(
+            "{} fixtures checked, {} with problems, {} problems",
+            self.files.len(),
+            self.files.values().filter(|p| !p.is_empty()).count(),
+            self.files.values().map(Vec::len).sum::<usize>()
+        ));
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,346 @@
+use crate::core::db::{Collection, Edge, Stub};
+use crate::core::op;
+
+use serde::Serialize;
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::str::FromStr;
+
+/// Direction of the edges to follow
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Direction {
+    /// From an entry to the types of its fields
+    Downstream,
+    /// From an entry to the entries that embed it
+    Upstream,
+    /// Both ways
+    Both,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "downstream" => Ok(Direction::Downstream),
+            "upstream" => Ok(Direction::Upstream),
+            "both" => Ok(Direction::Both),
+            _ => Err(format!("unknown direction: {}", s)),
+        }
+    }
+}
+
+/// A link of a cycle
+#[derive(Debug, Serialize)]
+pub struct CycleEdge {
+    pub from: String,
+    pub to: String,
+    pub fields: Vec<String>,
+    /// The fields that store `to` inline, without `Box`, `Vec` or another heap container
+    pub inline: Vec<String>,
+}
+
+/// Recursive types: a strongly connected component of the graph
+#[derive(Debug, Serialize)]
+pub struct Cycle {
+    pub ids: Vec<String>,
+    pub edges: Vec<CycleEdge>,
+    /// Some recursion goes through inline fields only
+    pub unboxed: bool,
+}
+
+/// Adjacency lists over the resolved edges of a collection
+pub struct Graph<'a> {
+    collection: &'a Collection,
+    outgoing: BTreeMap<&'a str, Vec<&'a Edge>>,
+    incoming: BTreeMap<&'a str, Vec<&'a Edge>>,
+}
+
+impl<'a> Graph<'a> {
+    /// constructor
+    pub fn new(collection: &'a Collection) -> Self {
+        let mut outgoing = BTreeMap::<&str, Vec<&Edge>>::new();
+        let mut incoming = BTreeMap::<&str, Vec<&Edge>>::new();
+        for e in collection.edges() {
+            outgoing.entry(&e.from).or_default().push(e);
+            incoming.entry(&e.to).or_default().push(e);
+        }
+        Self {
+            collection,
+            outgoing,
+            incoming,
+        }
+    }
+
+    /// Find the entry id of a type name given on the command line.
+    /// `block::Height` matches `block/height::Height`: the type name must be equal and the modules must appear in order.
+    pub fn find(&self, name: &str) -> Result<String, String> {
+        let candidates = self.candidates(name);
+        match candidates.len() {
+            1 => Ok(candidates[0].clone()),
+            0 => Err(format!("type not found: {}", name)),
+            _ => Err(format!(
+                "type name {} is ambiguous, candidates: {}",
+                name,
+                candidates.join(", ")
+            )),
+        }
+    }
+
+    /// Entry ids matching a type name, see `find`
+    pub fn candidates(&self, name: &str) -> Vec<String> {
+        if self.collection.get(name).is_some() {
+            return vec![name.to_string()];
+        }
+        let mut wanted = name.split("::").collect::<Vec<&str>>();
+        let type_name = wanted.pop().unwrap();
+        self.collection
+            .entries()
+            .map(|(id, _)| id)
+            .filter(|id| {
+                let mut modules = id
+                    .split("::")
+                    .flat_map(|s| s.split('/'))
+                    .collect::<Vec<&str>>();
+                if modules.pop() != Some(type_name) {
+                    return false;
+                }
+                let mut modules = modules.into_iter();
+                wanted.iter().all(|w| modules.any(|m| m == *w))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Edges of an entry in the given direction, with the id on the other end
+    pub fn neighbors(&self, id: &str, direction: Direction) -> Vec<(&'a Edge, &'a str)> {
+        let mut result = Vec::new();
+        if direction != Direction::Upstream {
+            for e in self.outgoing.get(id).into_iter().flatten() {
+                result.push((*e, e.to.as_str()));
+            }
+        }
+        if direction != Direction::Downstream {
+            for e in self.incoming.get(id).into_iter().flatten() {
+                result.push((*e, e.from.as_str()));
+            }
+        }
+        result
+    }
+
+    /// Breadth-first search from `id`. Returns the reached ids (without `id`) with their distance.
+    pub fn reach(
+        &self,
+        id: &str,
+        direction: Direction,
+        max_depth: Option<usize>,
+    ) -> Vec<(String, usize)> {
+        let mut seen = BTreeSet::<&str>::new();
+        let mut result = Vec::<(String, usize)>::new();
+        let mut queue = VecDeque::<(&str, usize)>::new();
+        seen.insert(id);
+        queue.push_back((id, 0));
+        while let Some((current, depth)) = queue.pop_front() {
+            if max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            for (_, next) in self.neighbors(current, direction) {
+                if seen.insert(next) {
+                    result.push((next.to_string(), depth + 1));
+                    queue.push_back((next, depth + 1));
+                }
+            }
+        }
+        result
+    }
+
+    /// Shortest chain of fields from `from` to `to`.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<&'a Edge>> {
+        let mut previous = BTreeMap::<&str, &Edge>::new();
+        let mut queue = VecDeque::<&str>::new();
+        queue.push_back(from);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = Vec::<&Edge>::new();
+                let mut id = to;
+                while id != from {
+                    let e = previous[id];
+                    path.insert(0, e);
+                    id = &e.from;
+                }
+                return Some(path);
+            }
+            for (e, next) in self.neighbors(current, Direction::Downstream) {
+                if next != from && !previous.contains_key(next) {
+                    previous.insert(next, e);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// The root and everything reachable from it
+    pub fn closure(&self, root: &str) -> BTreeSet<String> {
+        let mut result = self
+            .reach(root, Direction::Downstream, None)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<BTreeSet<String>>();
+        result.insert(root.to_string());
+        result
+    }
+
+    /// The entries within `radius` hops of the roots. The public neighbors outside of the radius
+    /// are collapsed into stubs.
+    pub fn focus(&self, roots: &[String], radius: usize, direction: Direction) -> Collection {
+        let mut ids = roots.iter().cloned().collect::<BTreeSet<String>>();
+        for root in roots {
+            ids.extend(
+                self.reach(root, direction, Some(radius))
+                    .into_iter()
+                    .map(|(id, _)| id),
+            );
+        }
+        let mut focused = self.collection.subset(&ids);
+        for id in &ids {
+            for (upstream, side) in [(false, Direction::Downstream), (true, Direction::Upstream)] {
+                if direction != Direction::Both && direction != side {
+                    continue;
+                }
+                let hidden = self
+                    .neighbors(id, side)
+                    .into_iter()
+                    .map(|(_, other)| other)
+                    .filter(|other| !ids.contains(*other))
+                    .filter(|other| self.collection.get(other).is_some_and(|e| e.public))
+                    .collect::<BTreeSet<&str>>();
+                if !hidden.is_empty() {
+                    focused.stubs.push(Stub {
+                        id: id.clone(),
+                        upstream,
+                        count: hidden.len(),
+                    });
+                }
+            }
+        }
+        focused
+    }
+
+    /// Fields of the edge that store the target inline
+    pub fn inline_fields(&self, e: &Edge) -> Vec<String> {
+        let entry = match self.collection.get(&e.from) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+        let name = e.to.rsplit("::").next().unwrap();
+        entry
+            .members
+            .iter()
+            .filter(|f| e.fields.contains(&f.name))
+            .filter(|f| {
+                syn::parse_str::<syn::Type>(&f.ty)
+                    .map(|ty| {
+                        op::get_inline_idents(&ty)
+                            .iter()
+                            .any(|i| i.rsplit("::").next() == Some(name))
+                    })
+                    .unwrap_or(false)
+            })
+            .map(|f| f.name.clone())
+            .collect()
+    }
+
+    /// Strongly connected components (Tarjan) over the edges accepted by `follow`.
+    /// Only the components that form a cycle are returned: more than one entry or an entry that refers to itself.
+    fn components(&self, follow: &dyn Fn(&Edge) -> bool) -> Vec<BTreeSet<&'a str>> {
+        struct State<'a> {
+            index: BTreeMap<&'a str, usize>,
+            low: BTreeMap<&'a str, usize>,
+            stack: Vec<&'a str>,
+            on_stack: BTreeSet<&'a str>,
+            result: Vec<BTreeSet<&'a str>>,
+        }
+
+        fn visit<'a>(
+            graph: &Graph<'a>,
+            follow: &dyn Fn(&Edge) -> bool,
+            state: &mut State<'a>,
+            id: &'a str,
+        ) {
+            let index = state.index.len();
+            state.index.insert(id, index);
+            state.low.insert(id, index);
+            state.stack.push(id);
+            state.on_stack.insert(id);
+            let mut looped = false;
+            for (e, next) in graph.neighbors(id, Direction::Downstream) {
+                if !follow(e) {
+                    continue;
+                }
+                looped |= next == id;
+                if !state.index.contains_key(next) {
+                    visit(graph, follow, state, next);
+                    let low = state.low[id].min(state.low[next]);
+                    state.low.insert(id, low);
+                } else if state.on_stack.contains(next) {
+                    let low = state.low[id].min(state.index[next]);
+                    state.low.insert(id, low);
+                }
+            }
+            if state.low[id] == state.index[id] {
+                let mut component = BTreeSet::new();
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(member);
+                    component.insert(member);
+                    if member == id {
+                        break;
+                    }
+                }
+                if component.len() > 1 || looped {
+                    state.result.push(component);
+                }
+            }
+        }
+
+        let mut state = State {
+            index: BTreeMap::new(),
+            low: BTreeMap::new(),
+            stack: Vec::new(),
+            on_stack: BTreeSet::new(),
+            result: Vec::new(),
+        };
+        for id in self.outgoing.keys() {
+            if !state.index.contains_key(id) {
+                visit(self, follow, &mut state, id);
+            }
+        }
+        state.result
+    }
+
+    /// Recursive types with the fields that form the cycles
+    pub fn cycles(&self) -> Vec<Cycle> {
+        let unboxed = self.components(&|e| !self.inline_fields(e).is_empty());
+        let mut cycles = self
+            .components(&|_| true)
+            .into_iter()
+            .map(|ids| Cycle {
+                edges: ids
+                    .iter()
+                    .flat_map(|id| self.neighbors(id, Direction::Downstream))
+                    .filter(|(_, to)| ids.contains(to))
+                    .map(|(e, _)| CycleEdge {
+                        from: e.from.clone(),
+                        to: e.to.clone(),
+                        fields: e.fields.clone(),
+                        inline: self.inline_fields(e),
+                    })
+                    .collect(),
+                unboxed: unboxed.iter().any(|u| u.is_subset(&ids)),
+                ids: ids.iter().map(|id| id.to_string()).collect(),
+            })
+            .collect::<Vec<Cycle>>();
+        cycles.sort_by(|a, b| a.ids.cmp(&b.ids));
+        cycles
+    }
+}
@@ -0,0 +1,183 @@
+use crate::core::db::{Collection, Color};
+use crate::core::template::Template;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// How to aggregate the entries
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GroupBy {
+    /// The `id_prefix` path of the entries: `block/header::Header` is in `block/header`
+    Module,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "module" => Ok(GroupBy::Module),
+            _ => Err(format!("unknown grouping: {}", s)),
+        }
+    }
+}
+
+/// Module of an entry id
+pub fn module_of(id: &str) -> &str {
+    id.rsplit_once("::").map(|(module, _)| module).unwrap_or("")
+}
+
+/// The aggregated entries of a module
+#[derive(Debug, Serialize)]
+pub struct Module {
+    pub name: String,
+    /// Number of entries by color
+    pub colors: BTreeMap<Color, usize>,
+}
+
+impl Module {
+    /// The most frequent color, used as the node color. Ties prefer serialization.
+    fn color(&self) -> Color {
+        self.colors
+            .iter()
+            .max_by_key(|(color, count)| {
+                (**count, **color != Color::White, std::cmp::Reverse(**color))
+            })
+            .map(|(color, _)| *color)
+            .unwrap_or(Color::White)
+    }
+
+    /// `green: 3, white: 1`
+    fn breakdown(&self, sep: &str) -> String {
+        self.colors
+            .iter()
+            .map(|(color, count)| format!("{}: {}", color, count))
+            .collect::<Vec<String>>()
+            .join(sep)
+    }
+}
+
+/// Field references from the entries of one module to the entries of another
+#[derive(Debug, Serialize)]
+pub struct Link {
+    pub from: String,
+    pub to: String,
+    /// Number of fields
+    pub weight: usize,
+}
+
+/// Module-level view of a collection
+#[derive(Debug, Serialize)]
+pub struct Grouping {
+    pub modules: Vec<Module>,
+    pub links: Vec<Link>,
+}
+
+impl Grouping {
+    /// Aggregate the public entries of the collection. With `only_json`, the entries without
+    /// serialization and the non-serialization links are left out, like in the type diagram.
+    pub fn new(collection: &Collection, only_json: bool) -> Self {
+        let mut modules = BTreeMap::<&str, Module>::new();
+        for (id, entry) in collection.entries() {
+            let color = entry.get_color();
+            if !entry.public || (only_json && color == Color::White) {
+                continue;
+            }
+            let module = module_of(id);
+            *modules
+                .entry(module)
+                .or_insert_with(|| Module {
+                    name: module.to_string(),
+                    colors: BTreeMap::new(),
+                })
+                .colors
+                .entry(color)
+                .or_default() += 1;
+        }
+        let mut weights = BTreeMap::<(&str, &str), usize>::new();
+        for e in collection.edges() {
+            let (from, to) = (module_of(&e.from), module_of(&e.to));
+            if from == to
+                || !modules.contains_key(from)
+                || !modules.contains_key(to)
+                || (only_json && !e.solid)
+            {
+                continue;
+            }
+            *weights.entry((from, to)).or_default() += e.fields.len().max(1);
+        }
+        Self {
+            links: weights
+                .into_iter()
+                .map(|((from, to), weight)| Link {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    weight,
+                })
+                .collect(),
+            modules: modules.into_values().collect(),
+        }
+    }
+
+    fn outgoing<'a>(&'a self, module: &'a str) -> impl Iterator<Item = &'a Link> {
+        self.links.iter().filter(move |l| l.from == module)
+    }
+
+    /// One line per module with the color breakdown, followed by the outgoing links
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::<String>::new();
+        for module in &self.modules {
+            lines.push(format!("{} ({})", module.name, module.breakdown(", ")));
+            for link in self.outgoing(&module.name) {
+                lines.push(format!("  -> {} ({})", link.to, link.weight));
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// draw.io diagram with one node per module. The line width shows the weight of the links.
+    pub fn to_csv(&self, template: &Template, no_header: bool) -> String {
+        let mut result = String::new();
+        if !no_header {
+            let colors = self
+                .modules
+                .iter()
+                .map(|m| m.color())
+                .collect::<Vec<Color>>();
+            result.push_str(template.render_modules(&colors).as_str());
+            result.push('\n');
+        }
+        for module in &self.modules {
+            let refs = |min: usize, max: usize| {
+                self.outgoing(&module.name)
+                    .filter(|l| l.weight >= min && l.weight <= max)
+                    .map(|l| l.to.clone())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            };
+            let deps = self
+                .outgoing(&module.name)
+                .map(|l| format!("{}: {}", l.to, l.weight))
+                .collect::<Vec<String>>();
+            result.push_str(
+                format!(
+                    "{},rectangle,{},{:?},{:?},{:?},{:?}\n",
+                    module.name,
+                    module.color(),
+                    refs(1, 1),
+                    refs(2, 4),
+                    refs(5, usize::MAX),
+                    [module.breakdown("<br>"), deps.join("<br>")]
+                        .iter()
+                        .filter(|s| !s.is_empty())
+                        .cloned()
+                        .collect::<Vec<String>>()
+                        .join("<br><br>"),
+                )
+                .as_str(),
+            );
+        }
+        result
+    }
+}
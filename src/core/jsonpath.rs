@@ -0,0 +1,587 @@
+use crate::core::attrs::Tagging;
+use crate::core::db::{Collection, Color, DataType, Entry, Field, Location, VariantKind};
+use crate::core::op::{generic_types, type_to_string};
+use crate::core::schema::{self, MAPS, POINTERS, SEQUENCES, SETS};
+
+use serde::Serialize;
+use std::fmt;
+use std::ops::Deref;
+use syn::Type;
+
+/// A member of a JSON path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Key(String),
+    /// Array index, `None` for `[*]`
+    Index(Option<usize>),
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, ".{}", key),
+            Segment::Index(Some(i)) => write!(f, "[{}]", i),
+            Segment::Index(None) => f.write_str("[*]"),
+        }
+    }
+}
+
+impl Segment {
+    /// The key or the `[index]`
+    fn name(&self) -> String {
+        match self {
+            Segment::Key(key) => format!("{:?}", key),
+            index => index.to_string(),
+        }
+    }
+}
+
+/// Parse `result.block.header`, `$.signatures[0].validator_address` or `$["@type"]`. Numeric keys are indexes too.
+pub fn parse(path: &str) -> Vec<Segment> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('.') {
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix("[\"") {
+            let end = r.find("\"]").unwrap_or(r.len());
+            segments.push(Segment::Key(r[..end].to_string()));
+            rest = r.get(end + 2..).unwrap_or("");
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').unwrap_or(r.len());
+            segments.push(Segment::Index(r[..end].parse().ok()));
+            rest = r.get(end + 1..).unwrap_or("");
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            let key = &rest[..end];
+            segments.push(match key.parse() {
+                Ok(i) => Segment::Index(Some(i)),
+                Err(_) => Segment::Key(key.to_string()),
+            });
+            rest = &rest[end..];
+        }
+    }
+    segments
+}
+
+/// One step of a JSON path in the Rust model
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Hop {
+    /// JSON path up to this step
+    pub path: String,
+    /// Rust item: a type, a field (`Type.field`), a variant (`Enum::Variant`) or a collection item
+    pub item: String,
+    /// Rust type of the value
+    pub ty: String,
+    /// Entry of the type, if it is defined in the analyzed source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry: Option<String>,
+    /// Serialization category of the entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// Custom serializers, flattened fields, tags and the reason the walk stopped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Walks a JSON path through the serde model of the `Serialize` side
+pub struct Walker<'a> {
+    collection: &'a Collection,
+}
+
+impl<'a> Walker<'a> {
+    pub fn new(collection: &'a Collection) -> Self {
+        Self { collection }
+    }
+
+    /// The hops from the root entry along the path, and whether the whole path was followed.
+    /// The last hop has the reason if the path could not be followed.
+    pub fn walk(&self, root: &str, segments: &[Segment]) -> (Vec<Hop>, bool) {
+        let mut hops = vec![Hop {
+            path: "$".to_string(),
+            item: short(root).to_string(),
+            ty: root.to_string(),
+            ..Hop::default()
+        }];
+        let followed = self.enter_entry(root, segments, "$", &mut hops);
+        (hops, followed)
+    }
+
+    /// Record the entry in the last hop and follow the path in it
+    fn enter_entry(&self, id: &str, segments: &[Segment], path: &str, hops: &mut Vec<Hop>) -> bool {
+        let entry = match self.collection.get(id) {
+            Some(entry) => entry,
+            None => return stop(hops, path, format!("{} is not defined in the source", id)),
+        };
+        if let Some(hop) = hops.last_mut() {
+            hop.entry = Some(id.to_string());
+            hop.color = Some(entry.get_color());
+            if hop.location.is_none() {
+                hop.location = entry.location().cloned();
+            }
+        }
+        if let Some(proxy) = &entry.serde.into {
+            note(hops, format!("serialized as {}", proxy));
+            return match syn::parse_str::<Type>(proxy) {
+                Ok(ty) => {
+                    let resolve = schema::proxy_resolver(self.collection);
+                    self.enter_type(&ty, &resolve, segments, path, hops)
+                }
+                Err(_) => stop(hops, path, format!("unparsed proxy type {}", proxy)),
+            };
+        }
+        if entry.serializer {
+            note(
+                hops,
+                custom(&format!("impl Serialize for {}", short(id)), segments),
+            );
+            return true;
+        }
+        if !entry.serialize {
+            return stop(
+                hops,
+                path,
+                format!("{} does not implement Serialize", short(id)),
+            );
+        }
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant().is_none() && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        match entry.r#type {
+            DataType::Struct
+                if fields.len() == 1 && (entry.serde.transparent || is_numbered(fields[0])) =>
+            {
+                let f = fields[0];
+                hops.push(field_hop(id, f, path));
+                note(hops, "newtype: serialized as its field".to_string());
+                self.enter_field(id, f, segments, path, hops)
+            }
+            _ if segments.is_empty() => true,
+            DataType::Struct => self.member(id, entry, &fields, segments, path, hops),
+            DataType::Enum => self.enum_member(id, entry, segments, path, hops),
+            DataType::Unknown => stop(hops, path, format!("{} is not defined in the source", id)),
+        }
+    }
+
+    /// A member of the object (or array) of the fields
+    fn member(
+        &self,
+        id: &str,
+        entry: &Entry,
+        fields: &[&Field],
+        segments: &[Segment],
+        path: &str,
+        hops: &mut Vec<Hop>,
+    ) -> bool {
+        let (segment, rest) = (&segments[0], &segments[1..]);
+        let next = format!("{}{}", path, segment);
+        let found = fields.iter().find(|f| match segment {
+            Segment::Key(key) => {
+                !f.serde.flatten && f.json_key(&entry.serde, true).as_deref() == Some(key.as_str())
+            }
+            Segment::Index(Some(i)) => fields.len() > 1 && f.ident() == i.to_string(),
+            Segment::Index(None) => false,
+        });
+        if let Some(f) = found {
+            hops.push(field_hop(id, f, &next));
+            return self.enter_field(id, f, rest, &next, hops);
+        }
+        // The flattened fields take the other keys
+        let flattened = fields
+            .iter()
+            .filter(|f| f.serde.flatten)
+            .map(|f| {
+                let mut attempt = vec![field_hop(id, f, path)];
+                note(&mut attempt, "flatten".to_string());
+                let resolved = self.enter_field(id, f, segments, path, &mut attempt);
+                (resolved, attempt)
+            })
+            .collect::<Vec<(bool, Vec<Hop>)>>();
+        if choose(flattened, hops) {
+            return true;
+        }
+        stop(
+            hops,
+            &next,
+            format!("no field {} in {}", segment.name(), short(id)),
+        )
+    }
+
+    /// A member of an enum: the tag, the variant or the members of the variant content
+    fn enum_member(
+        &self,
+        id: &str,
+        entry: &Entry,
+        segments: &[Segment],
+        path: &str,
+        hops: &mut Vec<Hop>,
+    ) -> bool {
+        let (segment, rest) = (&segments[0], &segments[1..]);
+        let key = match segment {
+            Segment::Key(key) => Some(key.as_str()),
+            Segment::Index(_) => None,
+        };
+        let next = format!("{}{}", path, segment);
+        let tagging = entry.serde.tagging();
+        match &tagging {
+            Tagging::Internal(tag) | Tagging::Adjacent(tag, _) if key == Some(tag.as_str()) => {
+                hops.push(Hop {
+                    path: next,
+                    item: format!("{} tag", short(id)),
+                    ty: "String".to_string(),
+                    note: Some("name of the variant".to_string()),
+                    ..Hop::default()
+                });
+                return rest.is_empty() || stop(hops, path, "the tag is a string".to_string());
+            }
+            Tagging::External => {
+                let variant = entry
+                    .get_variants()
+                    .into_iter()
+                    .find(|v| key.is_some() && v.json_name(&entry.serde, true).as_deref() == key);
+                return match variant {
+                    Some(variant) => {
+                        hops.push(Hop {
+                            path: next.clone(),
+                            item: format!("{}::{}", short(id), variant.name),
+                            ty: format!("{:?} variant", variant.kind),
+                            location: variant.location.clone(),
+                            ..Hop::default()
+                        });
+                        self.variant_content(
+                            id,
+                            entry,
+                            &variant.name,
+                            variant.kind,
+                            rest,
+                            &next,
+                            hops,
+                        )
+                    }
+                    None => stop(
+                        hops,
+                        &next,
+                        format!("no variant {} in {}", segment.name(), short(id)),
+                    ),
+                };
+            }
+            _ => {}
+        }
+        // Adjacent content, internally tagged and untagged variants: the first variant that has the member
+        let (segments, path) = match &tagging {
+            Tagging::Adjacent(_, content) if key == Some(content.as_str()) => {
+                // A longer path continues in a variant, whose hop is the content row
+                if rest.is_empty() {
+                    hops.push(Hop {
+                        path: next,
+                        item: format!("{} content", short(id)),
+                        ty: "variant content".to_string(),
+                        ..Hop::default()
+                    });
+                    return true;
+                }
+                (rest, next.as_str())
+            }
+            Tagging::Adjacent(_, _) => {
+                return stop(
+                    hops,
+                    &next,
+                    format!("no member {} in {}", segment.name(), short(id)),
+                )
+            }
+            _ => (segments, path),
+        };
+        let attempts = entry
+            .get_variants()
+            .into_iter()
+            .filter(|v| v.kind != VariantKind::Unit && v.json_name(&entry.serde, true).is_some())
+            .map(|variant| {
+                let mut attempt = vec![Hop {
+                    path: path.to_string(),
+                    item: format!("{}::{}", short(id), variant.name),
+                    ty: format!("{:?} variant", variant.kind),
+                    location: variant.location.clone(),
+                    ..Hop::default()
+                }];
+                let resolved = self.variant_content(
+                    id,
+                    entry,
+                    &variant.name,
+                    variant.kind,
+                    segments,
+                    path,
+                    &mut attempt,
+                );
+                (resolved, attempt)
+            })
+            .collect::<Vec<(bool, Vec<Hop>)>>();
+        choose(attempts, hops)
+            || stop(
+                hops,
+                path,
+                format!("no variant of {} has {}", short(id), segments[0].name()),
+            )
+    }
+
+    /// Follow the path in the content of a variant
+    #[allow(clippy::too_many_arguments)]
+    fn variant_content(
+        &self,
+        id: &str,
+        entry: &Entry,
+        variant: &str,
+        kind: VariantKind,
+        segments: &[Segment],
+        path: &str,
+        hops: &mut Vec<Hop>,
+    ) -> bool {
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant() == Some(variant) && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        match (kind, fields.as_slice()) {
+            (VariantKind::Tuple, [f]) => {
+                hops.push(field_hop(id, f, path));
+                self.enter_field(id, f, segments, path, hops)
+            }
+            _ if segments.is_empty() => true,
+            (VariantKind::Unit, _) => stop(hops, path, format!("{} is a unit variant", variant)),
+            _ => self.member(id, entry, &fields, segments, path, hops),
+        }
+    }
+
+    /// Follow the path in the type of a field
+    fn enter_field(
+        &self,
+        id: &str,
+        f: &Field,
+        segments: &[Segment],
+        path: &str,
+        hops: &mut Vec<Hop>,
+    ) -> bool {
+        if let Some(with) = &f.serde.with.ser {
+            note(hops, custom(with, segments));
+            return true;
+        }
+        match syn::parse_str::<Type>(&f.ty) {
+            Ok(ty) => {
+                let resolve = schema::field_resolver(self.collection, id, f);
+                self.enter_type(&ty, &resolve, segments, path, hops)
+            }
+            Err(_) => stop(hops, path, format!("unparsed type {}", f.ty)),
+        }
+    }
+
+    /// Follow the path in a Rust type. `resolve` finds the entry of a type name.
+    fn enter_type(
+        &self,
+        ty: &Type,
+        resolve: &dyn Fn(&str) -> Option<String>,
+        segments: &[Segment],
+        path: &str,
+        hops: &mut Vec<Hop>,
+    ) -> bool {
+        let item = |ty: &Type, segments: &[Segment], hops: &mut Vec<Hop>| match segments.first() {
+            Some(Segment::Index(i)) => {
+                let next = format!("{}{}", path, segments[0]);
+                hops.push(Hop {
+                    path: next.clone(),
+                    item: match i {
+                        Some(_) => "item".to_string(),
+                        None => "items".to_string(),
+                    },
+                    ty: type_to_string(ty),
+                    ..Hop::default()
+                });
+                self.enter_type(ty, resolve, &segments[1..], &next, hops)
+            }
+            Some(segment) => stop(
+                hops,
+                path,
+                format!(
+                    "{} is an array, not an object",
+                    path.to_owned() + &segment.to_string()
+                ),
+            ),
+            None => true,
+        };
+        match ty {
+            Type::Path(p) => {
+                let last = match p.path.segments.last() {
+                    Some(last) => last,
+                    None => return true,
+                };
+                let name = last.ident.to_string();
+                let args = generic_types(last);
+                if name == "Option" || POINTERS.contains(&name.as_str()) {
+                    match args.first() {
+                        Some(inner) => self.enter_type(inner, resolve, segments, path, hops),
+                        None => true,
+                    }
+                } else if SEQUENCES.contains(&name.as_str()) || SETS.contains(&name.as_str()) {
+                    match args.first() {
+                        Some(inner) => item(inner, segments, hops),
+                        None => true,
+                    }
+                } else if MAPS.contains(&name.as_str()) {
+                    match (segments.first(), args.get(1)) {
+                        (Some(segment), Some(value)) => {
+                            let next = format!("{}{}", path, segment);
+                            hops.push(Hop {
+                                path: next.clone(),
+                                item: "map value".to_string(),
+                                ty: type_to_string(value),
+                                ..Hop::default()
+                            });
+                            self.enter_type(value, resolve, &segments[1..], &next, hops)
+                        }
+                        _ => true,
+                    }
+                } else if let Some(id) = resolve(&name) {
+                    self.enter_entry(&id, segments, path, hops)
+                } else if segments.is_empty() {
+                    true
+                } else if schema::json_type(&name).is_some() {
+                    stop(
+                        hops,
+                        path,
+                        format!("{} is a {}", name, schema::json_type(&name).unwrap()),
+                    )
+                } else {
+                    stop(hops, path, format!("unresolved type {}", name))
+                }
+            }
+            Type::Reference(r) => self.enter_type(r.elem.deref(), resolve, segments, path, hops),
+            Type::Group(g) => self.enter_type(g.elem.deref(), resolve, segments, path, hops),
+            Type::Paren(p) => self.enter_type(p.elem.deref(), resolve, segments, path, hops),
+            Type::Slice(s) => item(s.elem.deref(), segments, hops),
+            Type::Array(a) => item(a.elem.deref(), segments, hops),
+            Type::Tuple(t) => match segments.first() {
+                Some(Segment::Index(Some(i))) if *i < t.elems.len() => {
+                    let next = format!("{}{}", path, segments[0]);
+                    hops.push(Hop {
+                        path: next.clone(),
+                        item: format!("tuple item {}", i),
+                        ty: type_to_string(&t.elems[*i]),
+                        ..Hop::default()
+                    });
+                    self.enter_type(&t.elems[*i], resolve, &segments[1..], &next, hops)
+                }
+                Some(segment) => stop(
+                    hops,
+                    path,
+                    format!("no item {} in the tuple", segment.name()),
+                ),
+                None => true,
+            },
+            _ => segments.is_empty(),
+        }
+    }
+}
+
+/// Aligned table of the hops: JSON path, Rust item, type, color and location, with the notes below the hops
+pub fn to_text(hops: &[Hop]) -> String {
+    let rows = hops
+        .iter()
+        .map(|h| {
+            [
+                h.path.clone(),
+                h.item.clone(),
+                h.ty.clone(),
+                h.color.map_or(String::new(), |c| c.to_string()),
+                h.location.as_ref().map_or(String::new(), |l| l.to_string()),
+            ]
+        })
+        .collect::<Vec<[String; 5]>>();
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.len());
+        }
+    }
+    let mut lines = Vec::new();
+    for (row, hop) in rows.iter().zip(hops) {
+        lines.push(
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| format!("{:<width$}", cell, width = widths[i]))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string(),
+        );
+        if let Some(note) = &hop.note {
+            lines.push(format!("{:width$}  ^ {}", "", note, width = widths[0]));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Hop of a field of the entry `id`
+fn field_hop(id: &str, f: &Field, path: &str) -> Hop {
+    Hop {
+        path: path.to_string(),
+        item: format!("{}.{}", short(id), f.name),
+        ty: f.ty.clone(),
+        location: f.location.clone(),
+        ..Hop::default()
+    }
+}
+
+/// Add a note to the last hop
+fn note(hops: &mut [Hop], note: String) {
+    if let Some(hop) = hops.last_mut() {
+        hop.note = Some(match hop.note.take() {
+            Some(previous) => format!("{}; {}", previous, note),
+            None => note,
+        });
+    }
+}
+
+/// Note of a custom serializer that takes over, with the rest of the path
+fn custom(serializer: &str, segments: &[Segment]) -> String {
+    if segments.is_empty() {
+        format!("custom serializer: {}", serializer)
+    } else {
+        format!(
+            "custom serializer: {} takes over, the rest of the path ({}) is not in the model",
+            serializer,
+            segments.iter().map(|s| s.to_string()).collect::<String>()
+        )
+    }
+}
+
+/// Add the hop where the path could not be followed
+fn stop(hops: &mut Vec<Hop>, path: &str, reason: String) -> bool {
+    hops.push(Hop {
+        path: path.to_string(),
+        note: Some(reason),
+        ..Hop::default()
+    });
+    false
+}
+
+/// Take the first attempt that follows the whole path
+fn choose(attempts: Vec<(bool, Vec<Hop>)>, hops: &mut Vec<Hop>) -> bool {
+    match attempts.into_iter().find(|(resolved, _)| *resolved) {
+        Some((_, attempt)) => {
+            hops.extend(attempt);
+            true
+        }
+        None => false,
+    }
+}
+
+fn short(id: &str) -> &str {
+    id.rsplit("::").next().unwrap_or(id)
+}
+
+fn is_numbered(f: &Field) -> bool {
+    f.ident().parse::<usize>().is_ok()
+}
@@ -1,6 +1,12 @@
-use quote::__private::TokenTree;
+use crate::core::db::{Field, Multiplicity};
+
+use proc_macro2::TokenTree;
+use quote::ToTokens;
+use std::collections::BTreeMap;
 use std::ops::Deref;
-use syn::{Attribute, GenericArgument, Path, PathArguments, ReturnType, Type};
+use syn::{
+    Attribute, GenericArgument, Item, Path, PathArguments, PathSegment, ReturnType, Type, UseTree,
+};
 
 pub fn get_idents_from_paths(path: &Path) -> Vec<String> {
     let mut ident: String = "".to_string();
@@ -10,18 +16,17 @@ pub fn get_idents_from_paths(path: &Path) -> Vec<String> {
     ident = ident.strip_prefix("::").unwrap().to_string();
     path.segments
         .iter()
-        .map(|path_segment| {
+        .flat_map(|path_segment| {
             let mut results = match &path_segment.arguments {
                 PathArguments::None => Vec::<String>::new(),
                 PathArguments::AngleBracketed(ab) => ab
                     .args
                     .iter()
-                    .map(|ga| match ga {
+                    .flat_map(|ga| match ga {
                         GenericArgument::Type(t) => get_idents_from_types(t),
                         GenericArgument::Binding(b) => get_idents_from_types(&b.ty),
                         _ => Vec::new(),
                     })
-                    .flatten()
                     .collect::<Vec<String>>(),
                 PathArguments::Parenthesized(pga) => match &pga.output {
                     ReturnType::Default => Vec::new(),
@@ -54,7 +59,6 @@ pub fn get_idents_from_paths(path: &Path) -> Vec<String> {
             }
             results
         })
-        .flatten()
         .collect::<Vec<String>>()
 }
 
@@ -71,12 +75,7 @@ pub fn get_idents_from_types(ty: &Type) -> Vec<String> {
         Type::Ptr(p) => get_idents_from_types(p.elem.deref()),
         Type::Reference(r) => get_idents_from_types(r.elem.deref()),
         Type::Slice(s) => get_idents_from_types(s.elem.deref()),
-        Type::Tuple(t) => t
-            .elems
-            .iter()
-            .map(|t| get_idents_from_types(t))
-            .flatten()
-            .collect(),
+        Type::Tuple(t) => t.elems.iter().flat_map(get_idents_from_types).collect(),
         _ => Vec::new(),
     }
 }
@@ -128,3 +127,200 @@ pub fn camelcase_to_snakecase(s: &str) -> String {
         })
         .collect()
 }
+
+/// Print a type as it would be written in the source: `Option<Vec<block::Id>>`
+pub fn type_to_string(ty: &Type) -> String {
+    tokens_to_string(ty)
+}
+
+/// Print an expression (like an enum discriminant or an array length) as it would be written in the source
+pub fn expr_to_string(expr: &syn::Expr) -> String {
+    tokens_to_string(expr)
+}
+
+/// Tokens without the spaces that `to_string` puts around the punctuation
+fn tokens_to_string(tokens: &dyn ToTokens) -> String {
+    let mut s = tokens.to_token_stream().to_string();
+    for (from, to) in &[
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        ("& ", "&"),
+        (" ;", ";"),
+        ("[ ", "["),
+        (" ]", "]"),
+        ("( ", "("),
+        (" )", ")"),
+    ] {
+        s = s.replace(from, to);
+    }
+    s
+}
+
+/// Remove the module paths from a type: `Option<block::Height>` -> `Option<Height>`
+pub fn strip_paths(ty: &str) -> String {
+    let mut result = String::new();
+    let mut segment = String::new();
+    let mut chars = ty.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            result.push_str(&segment);
+            segment.clear();
+            result.push(c);
+        }
+    }
+    result.push_str(&segment);
+    result
+}
+
+/// Containers that store their items on the heap: a type can contain itself through them
+const INDIRECTIONS: &[&str] = &[
+    "Box",
+    "Rc",
+    "Arc",
+    "Vec",
+    "VecDeque",
+    "LinkedList",
+    "BinaryHeap",
+    "BTreeMap",
+    "BTreeSet",
+    "HashMap",
+    "HashSet",
+];
+
+/// Type arguments of a path segment: `BTreeMap<String, Vec<u8>>` -> `[String, Vec<u8>]`
+pub fn generic_types(segment: &PathSegment) -> Vec<&Type> {
+    match &segment.arguments {
+        PathArguments::AngleBracketed(ab) => ab
+            .args
+            .iter()
+            .filter_map(|ga| match ga {
+                GenericArgument::Type(t) => Some(t),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Idents of a type that are stored inline, not behind a pointer or in a collection:
+/// `Option<block::Height>` -> `["block::Height", "Option"]`, `Vec<block::Height>` -> `[]`
+pub fn get_inline_idents(ty: &Type) -> Vec<String> {
+    match &ty {
+        Type::Path(p) => {
+            let last = match p.path.segments.last() {
+                Some(last) => last,
+                None => return Vec::new(),
+            };
+            if INDIRECTIONS.contains(&last.ident.to_string().as_str()) {
+                return Vec::new();
+            }
+            let mut results = generic_types(last)
+                .into_iter()
+                .flat_map(get_inline_idents)
+                .collect::<Vec<String>>();
+            results.push(
+                p.path
+                    .segments
+                    .iter()
+                    .map(|s| s.ident.to_string())
+                    .collect::<Vec<String>>()
+                    .join("::"),
+            );
+            results
+        }
+        Type::Array(a) => get_inline_idents(a.elem.deref()),
+        Type::Group(group) => get_inline_idents(group.elem.deref()),
+        Type::Paren(p) => get_inline_idents(p.elem.deref()),
+        Type::Tuple(t) => t.elems.iter().flat_map(get_inline_idents).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Multiplicity of the type `name` in a field type: `Option<Vec<block::Height>>` -> 0..* for `Height`.
+/// `None` if the type does not contain `name`.
+pub fn get_multiplicity(ty: &Type, name: &str) -> Option<Multiplicity> {
+    match &ty {
+        Type::Path(p) => {
+            let last = p.path.segments.last()?;
+            if last.ident == name {
+                return Some(Multiplicity::One);
+            }
+            let wrapper = match last.ident.to_string().as_str() {
+                "Option" => Multiplicity::Optional,
+                "Vec" | "VecDeque" | "LinkedList" | "BinaryHeap" | "BTreeSet" | "HashSet" => {
+                    Multiplicity::Many
+                }
+                "BTreeMap" | "HashMap" => Multiplicity::Keyed,
+                _ => Multiplicity::One,
+            };
+            let inner = generic_types(last)
+                .into_iter()
+                .find_map(|t| get_multiplicity(t, name));
+            inner.map(|m| m.max(wrapper))
+        }
+        Type::Array(a) => get_multiplicity(a.elem.deref(), name).map(|m| m.max(Multiplicity::Many)),
+        Type::Slice(s) => get_multiplicity(s.elem.deref(), name).map(|m| m.max(Multiplicity::Many)),
+        Type::Group(group) => get_multiplicity(group.elem.deref(), name),
+        Type::Paren(p) => get_multiplicity(p.elem.deref(), name),
+        Type::Ptr(p) => get_multiplicity(p.elem.deref(), name),
+        Type::Reference(r) => get_multiplicity(r.elem.deref(), name),
+        Type::Tuple(t) => t.elems.iter().find_map(|t| get_multiplicity(t, name)),
+        _ => None,
+    }
+}
+
+/// Tuple struct or newtype: the fields are numbered
+pub fn is_tuple(fields: &[&Field]) -> bool {
+    fields.iter().all(|f| f.ident().parse::<usize>().is_ok())
+}
+
+/// Imported names of the `use` declarations of a file (in any module) and their paths
+pub fn use_paths(items: &[Item]) -> BTreeMap<String, Vec<String>> {
+    fn tree(t: &UseTree, prefix: &mut Vec<String>, found: &mut BTreeMap<String, Vec<String>>) {
+        match t {
+            UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                tree(&p.tree, prefix, found);
+                prefix.pop();
+            }
+            UseTree::Name(n) => {
+                let mut path = prefix.clone();
+                path.push(n.ident.to_string());
+                found.insert(n.ident.to_string(), path);
+            }
+            UseTree::Rename(r) => {
+                let mut path = prefix.clone();
+                path.push(r.ident.to_string());
+                found.insert(r.rename.to_string(), path);
+            }
+            UseTree::Group(g) => {
+                for t in &g.items {
+                    tree(t, prefix, found);
+                }
+            }
+            UseTree::Glob(_) => {}
+        }
+    }
+    let mut found = BTreeMap::new();
+    for item in items {
+        match item {
+            Item::Use(u) => tree(&u.tree, &mut Vec::new(), &mut found),
+            Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    found.extend(use_paths(items));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
@@ -0,0 +1,514 @@
+use crate::core::attrs::Tagging;
+use crate::core::db::{Collection, DataType, Entry, Field, Variant, VariantKind};
+use crate::core::op::is_tuple;
+use crate::core::schema::{self, is_integer, shape, Generator, Shape};
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use syn::Type;
+
+/// Python keywords and the names used by the generated module
+const RESERVED: &str = "False None True and as assert async await break class continue def del elif else except \
+    finally for from global if import in is lambda nonlocal not or pass raise return try while with yield model_config";
+
+/// Python attribute name of a JSON key or Rust field
+fn attribute(name: &str) -> String {
+    let mut result = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .trim_start_matches('_')
+        .to_string();
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert_str(0, "f_");
+    }
+    if RESERVED.split_whitespace().any(|r| r == result) {
+        result.push('_');
+    }
+    result
+}
+
+/// Docstring
+fn docstring(doc: Option<&str>) -> Option<String> {
+    doc.map(|doc| {
+        let doc = doc.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+        if doc.contains('\n') {
+            format!(
+                "    \"\"\"{}\n    \"\"\"",
+                doc.lines()
+                    .enumerate()
+                    .map(|(i, line)| match (i, line.is_empty()) {
+                        (0, _) | (_, true) => line.to_string(),
+                        _ => format!("    {}", line),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            )
+        } else {
+            format!("    \"\"\"{}\"\"\"", doc)
+        }
+    })
+}
+
+/// `#` comment lines
+fn comment(doc: Option<&str>) -> String {
+    doc.map_or(String::new(), |doc| {
+        doc.lines()
+            .map(|line| format!("# {}", line).trim_end().to_string() + "\n")
+            .collect()
+    })
+}
+
+/// `name: ty` attribute of a JSON key, aliased if the key is not a Python name
+fn key_attribute(key: &str, ty: &str) -> String {
+    let attr = attribute(key);
+    if attr == key {
+        format!("    {}: {}", attr, ty)
+    } else {
+        format!("    {}: {} = Field(alias={:?})", attr, ty, key)
+    }
+}
+
+/// Type or model class of an enum variant
+enum Payload {
+    Type(String),
+    Model(String),
+}
+
+/// A generated class or type alias
+struct Declaration {
+    code: String,
+    /// Entries used by the declaration, they are declared first
+    uses: BTreeSet<String>,
+    /// Names of the pydantic models, for `model_rebuild`
+    models: Vec<String>,
+}
+
+/// pydantic v2 models of the JSON written by `Serialize`
+pub struct Models<'a> {
+    collection: &'a Collection,
+    names: BTreeMap<String, String>,
+    /// Entries referenced by the current declaration
+    refs: BTreeSet<String>,
+    /// Models of the current declaration
+    models: Vec<String>,
+}
+
+impl<'a> Models<'a> {
+    pub fn new(collection: &'a Collection) -> Self {
+        Self {
+            collection,
+            names: schema::type_names(collection),
+            refs: BTreeSet::new(),
+            models: Vec::new(),
+        }
+    }
+
+    /// Python module with the models of the serializable entries and the types they use
+    pub fn render(&mut self) -> String {
+        let mut declarations = BTreeMap::<String, Declaration>::new();
+        let mut queue = Generator::roots(self.collection, true)
+            .into_iter()
+            .collect::<VecDeque<String>>();
+        while let Some(id) = queue.pop_front() {
+            if declarations.contains_key(&id) {
+                continue;
+            }
+            let code = self.declaration(&id);
+            let uses = std::mem::take(&mut self.refs);
+            queue.extend(uses.iter().cloned());
+            declarations.insert(
+                id,
+                Declaration {
+                    code,
+                    uses,
+                    models: std::mem::take(&mut self.models),
+                },
+            );
+        }
+
+        // Type aliases are evaluated when the module is loaded: the used types go first
+        let mut order = Vec::<&str>::new();
+        let mut visited = BTreeSet::<&str>::new();
+        for id in declarations.keys() {
+            visit(id, &declarations, &mut visited, &mut order);
+        }
+
+        let mut result = String::from(
+            "from __future__ import annotations
+
+from typing import Annotated, Any, Dict, List, Literal, Optional, Tuple, Union
+
+from pydantic import BaseModel, ConfigDict, Field
+",
+        );
+        for id in &order {
+            result.push_str("\n\n");
+            result.push_str(&declarations[*id].code);
+        }
+        let models = order
+            .iter()
+            .flat_map(|id| declarations[*id].models.iter())
+            .collect::<Vec<&String>>();
+        if !models.is_empty() {
+            result.push_str("\n\n");
+            for model in models {
+                result.push_str(format!("{}.model_rebuild()\n", model).as_str());
+            }
+        }
+        result
+    }
+
+    fn declaration(&mut self, id: &str) -> String {
+        let entry = match self.collection.get(id) {
+            Some(entry) => entry,
+            None => return String::new(),
+        };
+        let name = self.names[id].clone();
+        if let Some(proxy) = &entry.serde.into {
+            let resolve = schema::proxy_resolver(self.collection);
+            let ty = match syn::parse_str::<Type>(proxy) {
+                Ok(ty) => self.type_name(&ty, &resolve),
+                Err(_) => "Any".to_string(),
+            };
+            return format!("# Serialized as {}\n{} = {}\n", proxy, name, ty);
+        }
+        if entry.serializer || !entry.serialize {
+            return format!(
+                "# TODO: {} has a custom Serialize implementation{}, its JSON shape is not derived.\n{} = Any\n",
+                id,
+                entry
+                    .location()
+                    .map_or(String::new(), |l| format!(" ({})", l)),
+                name
+            );
+        }
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant().is_none() && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        match entry.r#type {
+            DataType::Struct if entry.serde.transparent || is_tuple(&fields) => {
+                let ty = self.tuple(id, &fields);
+                format!("{}{} = {}\n", comment(entry.doc()), name, ty)
+            }
+            DataType::Struct => self.model(id, entry, &name, &fields, Vec::new(), entry.doc()),
+            DataType::Enum => self.union(id, entry, &name),
+            DataType::Unknown => format!("{} = Any\n", name),
+        }
+    }
+
+    /// `Tuple[A, B]` of tuple fields, `A` of a newtype, `None` without fields
+    fn tuple(&mut self, id: &str, fields: &[&Field]) -> String {
+        let types = fields
+            .iter()
+            .map(|f| self.field_type(id, f))
+            .collect::<Vec<String>>();
+        match types.as_slice() {
+            [] => "None".to_string(),
+            [newtype] => newtype.clone(),
+            _ => format!("Tuple[{}]", types.join(", ")),
+        }
+    }
+
+    /// Model class of named fields, after the `head` attributes (the tag and content of enum variants).
+    /// Flattened structs are base classes.
+    fn model(
+        &mut self,
+        id: &str,
+        entry: &Entry,
+        name: &str,
+        fields: &[&Field],
+        head: Vec<String>,
+        doc: Option<&str>,
+    ) -> String {
+        let mut bases = Vec::<String>::new();
+        let mut config = Vec::<&str>::new();
+        let mut aliases = head.iter().any(|line| line.contains("alias="));
+        let mut lines = head;
+        for f in fields {
+            let ty = self.field_type(id, f);
+            if f.serde.flatten {
+                // Flattened maps keep the unknown keys, flattened models are inherited
+                if ty.starts_with("Dict[") {
+                    config.push("extra=\"allow\"");
+                } else {
+                    bases.push(ty);
+                }
+                continue;
+            }
+            let key = match f.json_key(&entry.serde, true) {
+                Some(key) => key,
+                None => continue,
+            };
+            let attr = attribute(f.ident());
+            let optional = f.ty.starts_with("Option<")
+                || f.serde.default
+                || entry.serde.default
+                || f.serde.skip_serializing_if.is_some();
+            let ty = if optional && !ty.starts_with("Optional[") {
+                format!("Optional[{}]", ty)
+            } else {
+                ty
+            };
+            let mut args = Vec::<String>::new();
+            if optional {
+                args.push("default=None".to_string());
+            }
+            if attr != key {
+                aliases = true;
+                args.push(format!("alias={:?}", key));
+            }
+            if let Some(doc) = &f.doc {
+                args.push(format!(
+                    "description={:?}",
+                    doc.split_whitespace().collect::<Vec<&str>>().join(" ")
+                ));
+            }
+            lines.push(match args.as_slice() {
+                [] => format!("    {}: {}", attr, ty),
+                [default] if default == "default=None" => format!("    {}: {} = None", attr, ty),
+                _ => format!("    {}: {} = Field({})", attr, ty, args.join(", ")),
+            });
+        }
+        if aliases {
+            config.push("populate_by_name=True");
+        }
+        if entry.serde.deny_unknown_fields {
+            config.push("extra=\"forbid\"");
+        }
+        if bases.is_empty() {
+            bases.push("BaseModel".to_string());
+        }
+        let mut body = docstring(doc).into_iter().collect::<Vec<String>>();
+        if !config.is_empty() {
+            body.push(format!(
+                "    model_config = ConfigDict({})",
+                config.join(", ")
+            ));
+        }
+        body.extend(lines);
+        if body.is_empty() {
+            body.push("    pass".to_string());
+        }
+        self.models.push(name.to_string());
+        format!(
+            "class {}({}):\n{}\n",
+            name,
+            bases.join(", "),
+            body.join("\n")
+        )
+    }
+
+    /// Union of the variants, following the enum tagging. Variants with content are model classes.
+    fn union(&mut self, id: &str, entry: &Entry, name: &str) -> String {
+        let tagging = entry.serde.tagging();
+        let mut classes = Vec::<String>::new();
+        let mut members = Vec::<String>::new();
+        for variant in entry.get_variants() {
+            let json_name = match variant.json_name(&entry.serde, true) {
+                Some(json_name) => json_name,
+                None => continue,
+            };
+            let class = format!("{}{}", name, variant.name);
+            match self.payload(id, entry, &variant, &class, &tagging, &json_name) {
+                Payload::Type(ty) => members.push(ty),
+                Payload::Model(code) => {
+                    classes.push(code);
+                    members.push(class);
+                }
+            }
+        }
+        let ty = match members.as_slice() {
+            [] => "Any".to_string(),
+            [one] => one.clone(),
+            _ => match &tagging {
+                Tagging::External if members.iter().all(|m| m.starts_with("Literal[")) => format!(
+                    "Literal[{}]",
+                    members
+                        .iter()
+                        .map(|m| &m["Literal[".len()..m.len() - 1])
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ),
+                Tagging::Internal(tag) | Tagging::Adjacent(tag, _) => format!(
+                    "Annotated[Union[{}], Field(discriminator={:?})]",
+                    members.join(", "),
+                    attribute(tag)
+                ),
+                _ => format!("Union[{}]", members.join(", ")),
+            },
+        };
+        classes.push(format!("{}{} = {}\n", comment(entry.doc()), name, ty));
+        classes.join("\n\n")
+    }
+
+    /// Type or model class of a variant: a `Literal` tag, an `{"Variant": content}` object, a tagged object or the
+    /// untagged content
+    fn payload(
+        &mut self,
+        id: &str,
+        entry: &Entry,
+        variant: &Variant,
+        class: &str,
+        tagging: &Tagging,
+        json_name: &str,
+    ) -> Payload {
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant() == Some(variant.name.as_str()) && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        let doc = variant.doc.as_deref();
+        let literal = format!("Literal[{:?}]", json_name);
+        match (tagging, variant.kind) {
+            (Tagging::External, VariantKind::Unit) => Payload::Type(literal),
+            (Tagging::Untagged, VariantKind::Unit) => Payload::Type("None".to_string()),
+            (Tagging::Untagged, VariantKind::Tuple) => Payload::Type(self.tuple(id, &fields)),
+            (Tagging::Untagged, VariantKind::Struct) => {
+                Payload::Model(self.model(id, entry, class, &fields, Vec::new(), doc))
+            }
+            (Tagging::Internal(tag), VariantKind::Struct) => {
+                let head = vec![key_attribute(tag, &literal)];
+                Payload::Model(self.model(id, entry, class, &fields, head, doc))
+            }
+            (Tagging::Internal(tag), VariantKind::Tuple) => {
+                // The tag is added to the fields of the newtype content: the class extends its model
+                let content = self.tuple(id, &fields);
+                let head = vec![key_attribute(tag, &literal)];
+                let code = self.model(id, entry, class, &[], head, doc);
+                if self.is_model(&content) {
+                    Payload::Model(code.replacen("(BaseModel)", &format!("({})", content), 1))
+                } else {
+                    Payload::Model(code.replacen(
+                        "):\n",
+                        &format!("):\n    # TODO: the fields of {} are not checked\n    model_config = ConfigDict(extra=\"allow\")\n", content),
+                        1,
+                    ))
+                }
+            }
+            (Tagging::Internal(tag), VariantKind::Unit)
+            | (Tagging::Adjacent(tag, _), VariantKind::Unit) => {
+                let head = vec![key_attribute(tag, &literal)];
+                Payload::Model(self.model(id, entry, class, &[], head, doc))
+            }
+            (Tagging::External, _) | (Tagging::Adjacent(_, _), _) => {
+                // Struct variants get a content class
+                let mut code = String::new();
+                let content = match variant.kind {
+                    VariantKind::Struct => {
+                        let content = format!("{}Content", class);
+                        code = self.model(id, entry, &content, &fields, Vec::new(), None);
+                        code.push_str("\n\n");
+                        content
+                    }
+                    _ => self.tuple(id, &fields),
+                };
+                let head = match tagging {
+                    Tagging::Adjacent(tag, key) => {
+                        vec![key_attribute(tag, &literal), key_attribute(key, &content)]
+                    }
+                    _ => vec![key_attribute(json_name, &content)],
+                };
+                code.push_str(&self.model(id, entry, class, &[], head, doc));
+                Payload::Model(code)
+            }
+        }
+    }
+
+    /// Whether a Python type is the model class of a struct
+    fn is_model(&self, ty: &str) -> bool {
+        self.names
+            .iter()
+            .find(|(_, name)| name.as_str() == ty)
+            .and_then(|(id, _)| self.collection.get(id))
+            .is_some_and(|entry| {
+                let fields = entry
+                    .members
+                    .iter()
+                    .filter(|f| !f.serde.skip_serializing)
+                    .collect::<Vec<&Field>>();
+                entry.r#type == DataType::Struct
+                    && entry.serialize
+                    && !entry.serializer
+                    && entry.serde.into.is_none()
+                    && !entry.serde.transparent
+                    && !is_tuple(&fields)
+            })
+    }
+
+    /// Type of a field. Integers with a custom serializer are strings.
+    fn field_type(&mut self, id: &str, f: &Field) -> String {
+        if f.serde.with.ser.is_some() {
+            return match (is_integer(&f.ty), f.ty.starts_with("Option<")) {
+                (true, true) => "Optional[str]".to_string(),
+                (true, false) => "str".to_string(),
+                _ => "Any".to_string(),
+            };
+        }
+        match syn::parse_str::<Type>(&f.ty) {
+            Ok(ty) => {
+                let resolve = schema::field_resolver(self.collection, id, f);
+                self.type_name(&ty, &resolve)
+            }
+            Err(_) => "Any".to_string(),
+        }
+    }
+
+    /// Python type of a Rust type. `resolve` finds the entry of a type name.
+    fn type_name(&mut self, ty: &Type, resolve: &dyn Fn(&str) -> Option<String>) -> String {
+        match shape(ty) {
+            Shape::Optional(t) => format!("Optional[{}]", self.type_name(t, resolve)),
+            Shape::Array { item, .. } => format!("List[{}]", self.type_name(item, resolve)),
+            Shape::Map(value) => format!("Dict[str, {}]", self.type_name(value, resolve)),
+            Shape::Tuple(elems) if elems.is_empty() => "None".to_string(),
+            Shape::Tuple(elems) => format!(
+                "Tuple[{}]",
+                elems
+                    .iter()
+                    .map(|e| self.type_name(e, resolve))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Shape::Named(name) => {
+                if let Some(id) = resolve(&name) {
+                    self.refs.insert(id.clone());
+                    self.names[&id].clone()
+                } else if let Some(json) = schema::json_type(&name) {
+                    py_type(json).to_string()
+                } else {
+                    "Any".to_string()
+                }
+            }
+            Shape::Unknown => "Any".to_string(),
+        }
+    }
+}
+
+/// Python type of a JSON type
+fn py_type(json: &str) -> &'static str {
+    match json {
+        "boolean" => "bool",
+        "integer" => "int",
+        "number" => "float",
+        _ => "str",
+    }
+}
+
+/// Depth-first order of the declarations, the used ones first
+fn visit<'a>(
+    id: &'a str,
+    declarations: &'a BTreeMap<String, Declaration>,
+    visited: &mut BTreeSet<&'a str>,
+    order: &mut Vec<&'a str>,
+) {
+    if !visited.insert(id) {
+        return;
+    }
+    if let Some(declaration) = declarations.get(id) {
+        for used in &declaration.uses {
+            visit(used, declarations, visited, order);
+        }
+        order.push(id);
+    }
+}
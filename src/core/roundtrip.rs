@@ -0,0 +1,283 @@
+use crate::core::db::{Collection, Color, DataType, Entry, Field};
+use crate::core::op::{camelcase_to_snakecase, generic_types};
+use crate::core::sample::Sampler;
+
+use std::ops::Deref;
+use syn::Type;
+
+/// Primitives with a proptest `any` strategy that survives a JSON round trip (floats may not)
+const ARBITRARY: &[&str] = &[
+    "bool", "char", "String", "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64",
+    "usize",
+];
+
+/// Rust path of an entry in the crate: `block/parts::Header` -> `tendermint::block::parts::Header`.
+/// `lib.rs`, `main.rs` and `mod.rs` are the module of their folder.
+fn rust_path(id: &str, crate_name: &str, qualified: bool) -> String {
+    let mut parts = id
+        .split("::")
+        .flat_map(|s| s.split('/'))
+        .collect::<Vec<&str>>();
+    let name = parts.pop().unwrap_or(id);
+    if !qualified {
+        parts.insert(0, crate_name);
+    }
+    if parts.last() == Some(&"mod") || (parts.len() == 2 && matches!(parts[1], "lib" | "main")) {
+        parts.pop();
+    }
+    parts.push(name);
+    parts
+        .iter()
+        .map(|p| p.replace('-', "_"))
+        .collect::<Vec<String>>()
+        .join("::")
+}
+
+/// Test function name of an entry: `block/parts::Header` -> `block_parts_header`
+fn test_name(id: &str) -> String {
+    id.split("::")
+        .flat_map(|s| s.split('/'))
+        .map(|s| camelcase_to_snakecase(s).replace('-', "_"))
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+/// Why a gradient entry doesn't read back what it writes
+fn asymmetry(entry: &Entry) -> String {
+    let side = |ser: bool, what: &str| {
+        if ser {
+            format!("{} for Serialize only", what)
+        } else {
+            format!("{} for Deserialize only", what)
+        }
+    };
+    let mut reasons = Vec::new();
+    if entry.serialize != entry.deserialize {
+        reasons.push(side(entry.serialize, "derive"));
+    }
+    if entry.serde_into != entry.serde_from {
+        reasons.push(side(entry.serde_into, "proxy type"));
+    }
+    if entry.serializer != entry.deserializer {
+        reasons.push(side(entry.serializer, "custom impl"));
+    }
+    format!("asymmetric serialization: {}", reasons.join(", "))
+}
+
+/// Whether both `Serialize` and `Deserialize` are implemented
+fn symmetric_traits(entry: &Entry) -> bool {
+    (entry.serialize || entry.serializer || entry.serde_into)
+        && (entry.deserialize || entry.deserializer || entry.serde_from)
+}
+
+/// Rust test module with a round-trip test (`to_string` -> `from_str` -> `assert_eq`) of every serializable public
+/// type (green, blue and yellow). The values of derived (green) structs come from proptest strategies when every
+/// field is public and of a primitive or std collection type, from the sample JSON otherwise.
+pub struct RoundTrip<'a> {
+    collection: &'a Collection,
+    crate_name: String,
+}
+
+impl<'a> RoundTrip<'a> {
+    pub fn new(collection: &'a Collection, crate_name: &str) -> Self {
+        Self {
+            collection,
+            crate_name: crate_name.replace('-', "_"),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let qualified = self.collection.sources.iter().any(|s| s.prefix.is_some());
+        let mut tests = Vec::new();
+        let mut proptest = false;
+        for (id, entry) in self.collection.entries() {
+            let testable = !matches!(entry.get_color(), Color::White | Color::Red);
+            if !entry.public || !testable || entry.r#type == DataType::Unknown || id.contains('.') {
+                continue;
+            }
+            let path = rust_path(id, &self.crate_name, qualified);
+            let name = test_name(id);
+            let mut header = format!("/// {} ({})\n", id, entry.get_color());
+            if entry.get_color().is_gradient() {
+                header.push_str(format!("#[ignore = \"{}\"]\n", asymmetry(entry)).as_str());
+                if !symmetric_traits(entry) {
+                    tests.push(format!(
+                        "{}#[test]\nfn {}() {{\n    // {} can't be read back, there is nothing to round-trip.\n}}\n",
+                        header,
+                        name,
+                        path
+                    ));
+                    continue;
+                }
+            }
+            let check = if entry.partial_eq {
+                "round_trip"
+            } else {
+                "round_trip_json"
+            };
+            if let Some((args, value)) = strategy(self.collection, id, entry, &path) {
+                proptest = true;
+                tests.push(format!(
+                    "proptest! {{\n    {}    #[test]\n    fn {}({}) {{\n        {}_value({});\n    }}\n}}\n",
+                    header.replace('\n', "\n    ").trim_end_matches(' '),
+                    name,
+                    args,
+                    check,
+                    value
+                ));
+                continue;
+            }
+            let mut sampler = Sampler::new(self.collection);
+            let sample = serde_json::to_string_pretty(&sampler.sample(id)).unwrap();
+            // The placeholder of a `try_from` proxy type is rarely a valid value
+            let validated = sampler
+                .visited()
+                .iter()
+                .any(|v| self.collection.get(v).is_some_and(|e| e.serde_from));
+            if !entry.get_color().is_gradient() {
+                if sample.contains("\"<") {
+                    header.push_str(
+                        "#[ignore = \"replace the <...> placeholders of the sample JSON\"]\n",
+                    );
+                } else if validated {
+                    header.push_str("#[ignore = \"replace the sample with a valid value\"]\n");
+                }
+            }
+            tests.push(format!(
+                "{}#[test]\nfn {}() {{\n    {}::<{}>(\n        r#\"{}\"#,\n    );\n}}\n",
+                header,
+                name,
+                check,
+                path,
+                sample.replace('\n', "\n        ")
+            ));
+        }
+        let mut module = format!(
+            "//! Round-trip serialization tests of the public types of `{}`.
+//! Each test deserializes a sample JSON (or takes a proptest value), serializes it and reads it back.
+",
+            self.crate_name
+        );
+        if proptest {
+            module.push_str("use proptest::prelude::*;\n");
+        }
+        module.push_str(
+            "use serde::{de::DeserializeOwned, Serialize};
+use std::fmt::Debug;
+
+/// `json` -> value -> JSON -> value, the values must be equal
+#[allow(dead_code)]
+fn round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(json: &str) {
+    let value: T = serde_json::from_str(json).expect(\"deserialize the sample\");
+    round_trip_value(value);
+}
+
+/// value -> JSON -> value, the values must be equal
+#[allow(dead_code)]
+fn round_trip_value<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: T) {
+    let serialized = serde_json::to_string(&value).expect(\"serialize\");
+    let again: T = serde_json::from_str(&serialized).expect(\"deserialize\");
+    assert_eq!(value, again);
+}
+
+/// `json` -> value -> JSON -> value, for the types without `PartialEq`
+#[allow(dead_code)]
+fn round_trip_json<T: Serialize + DeserializeOwned>(json: &str) {
+    let value: T = serde_json::from_str(json).expect(\"deserialize the sample\");
+    round_trip_json_value(value);
+}
+
+/// value -> JSON -> value -> JSON, the JSON must be equal
+#[allow(dead_code)]
+fn round_trip_json_value<T: Serialize + DeserializeOwned>(value: T) {
+    let serialized = serde_json::to_string(&value).expect(\"serialize\");
+    let again: T = serde_json::from_str(&serialized).expect(\"deserialize\");
+    assert_eq!(serialized, serde_json::to_string(&again).expect(\"serialize again\"));
+}
+",
+        );
+        for test in tests {
+            module.push('\n');
+            module.push_str(&test);
+        }
+        module
+    }
+}
+
+/// Arguments of a proptest function and the constructor of the value, for derived structs with public fields that
+/// have strategies. Skipped fields would not round-trip, and a value built field by field doesn't go through the
+/// proxy type or custom impl of the struct or of its members.
+fn strategy(
+    collection: &Collection,
+    id: &str,
+    entry: &Entry,
+    path: &str,
+) -> Option<(String, String)> {
+    if entry.r#type != DataType::Struct || entry.get_color() != Color::Green {
+        return None;
+    }
+    let derived = collection.edges().iter().filter(|e| e.from == id).all(|e| {
+        collection
+            .get(&e.to)
+            .is_none_or(|m| m.get_color() == Color::Green)
+    });
+    if !derived {
+        return None;
+    }
+    let fields = entry.members.iter().collect::<Vec<&Field>>();
+    if fields.is_empty() {
+        return None;
+    }
+    let mut args = Vec::new();
+    let mut values = Vec::new();
+    for (i, f) in fields.iter().enumerate() {
+        if !f.public || f.serde.skip_serializing || f.serde.skip_deserializing {
+            return None;
+        }
+        let ty = syn::parse_str::<Type>(&f.ty).ok()?;
+        args.push(format!("v{} in {}", i, type_strategy(&ty)?));
+        values.push(match f.ident().parse::<usize>() {
+            Ok(_) => format!("v{}", i),
+            Err(_) => format!("{}: v{}", f.ident(), i),
+        });
+    }
+    let value = if fields[0].ident().parse::<usize>().is_ok() {
+        format!("{}({})", path, values.join(", "))
+    } else {
+        format!("{} {{ {} }}", path, values.join(", "))
+    };
+    Some((args.join(", "), value))
+}
+
+/// proptest strategy of a primitive or std collection type
+fn type_strategy(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(p) => {
+            let last = p.path.segments.last()?;
+            let name = last.ident.to_string();
+            let args = generic_types(last);
+            let arg = |i: usize| args.get(i).and_then(|t| type_strategy(t));
+            match name.as_str() {
+                n if ARBITRARY.contains(&n) && args.is_empty() => Some(format!("any::<{}>()", n)),
+                "Option" => Some(format!("proptest::option::of({})", arg(0)?)),
+                "Box" => Some(format!("{}.prop_map(Box::new)", arg(0)?)),
+                "Vec" => Some(format!("proptest::collection::vec({}, 0..3)", arg(0)?)),
+                "BTreeMap" if args.first().is_some_and(|k| is_string(k)) => Some(format!(
+                    "proptest::collection::btree_map(any::<String>(), {}, 0..3)",
+                    arg(1)?
+                )),
+                "HashMap" if args.first().is_some_and(|k| is_string(k)) => Some(format!(
+                    "proptest::collection::hash_map(any::<String>(), {}, 0..3)",
+                    arg(1)?
+                )),
+                _ => None,
+            }
+        }
+        Type::Paren(p) => type_strategy(p.elem.deref()),
+        _ => None,
+    }
+}
+
+fn is_string(ty: &Type) -> bool {
+    matches!(ty, Type::Path(p) if p.path.is_ident("String"))
+}
@@ -0,0 +1,211 @@
+use crate::core::attrs::{ContainerAttrs, Tagging};
+use crate::core::db::{Collection, DataType, Entry, Field, VariantKind};
+use crate::core::schema::{self, is_integer, shape, Shape};
+
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use syn::Type;
+
+/// Placeholder value of a JSON type
+fn placeholder(json: &str) -> Value {
+    match json {
+        "boolean" => json!(false),
+        "integer" => json!(0),
+        "number" => json!(0.0),
+        _ => json!("string"),
+    }
+}
+
+/// Representative JSON value written by `Serialize`: every field is present, `Option`s are `Some`, collections have
+/// one item and enums take their first variant. Recursive types, custom implementations and unresolved types are
+/// `"<...>"` strings that tell why they were not expanded.
+pub struct Sampler<'a> {
+    collection: &'a Collection,
+    /// Entries being expanded, to stop at recursion
+    stack: Vec<String>,
+    /// Entries met while sampling
+    visited: BTreeSet<String>,
+}
+
+impl<'a> Sampler<'a> {
+    pub fn new(collection: &'a Collection) -> Self {
+        Self {
+            collection,
+            stack: Vec::new(),
+            visited: BTreeSet::new(),
+        }
+    }
+
+    /// Entries met by the samples so far
+    pub fn visited(&self) -> &BTreeSet<String> {
+        &self.visited
+    }
+
+    /// Sample of an entry
+    pub fn sample(&mut self, id: &str) -> Value {
+        let entry = match self.collection.get(id) {
+            Some(entry) => entry,
+            None => return json!(format!("<unknown type {}>", id)),
+        };
+        self.visited.insert(id.to_string());
+        let name = id.rsplit("::").next().unwrap_or(id);
+        if self.stack.iter().any(|s| s == id) {
+            return json!(format!("<recursive {}>", name));
+        }
+        if let Some(proxy) = &entry.serde.into {
+            return match syn::parse_str::<Type>(proxy) {
+                Ok(ty) => {
+                    let resolve = schema::proxy_resolver(self.collection);
+                    self.stack.push(id.to_string());
+                    let value = self.type_value(&ty, &resolve);
+                    self.stack.pop();
+                    value
+                }
+                Err(_) => json!(format!("<{} serialized as {}>", name, proxy)),
+            };
+        }
+        if entry.serializer || !entry.serialize {
+            return json!(format!(
+                "<{}: custom Serialize{}>",
+                name,
+                entry
+                    .location()
+                    .map_or(String::new(), |l| format!(" at {}", l))
+            ));
+        }
+        self.stack.push(id.to_string());
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant().is_none() && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        let value = match entry.r#type {
+            DataType::Struct if entry.serde.transparent => match fields.first() {
+                Some(f) => self.field_value(id, f),
+                None => Value::Null,
+            },
+            DataType::Struct => self.fields_value(id, &fields, &entry.serde),
+            DataType::Enum => self.enum_value(id, entry),
+            DataType::Unknown => json!(format!("<{}: not defined in the source>", name)),
+        };
+        self.stack.pop();
+        value
+    }
+
+    /// Object of the named fields, array of the tuple fields, `null` for unit structs
+    fn fields_value(&mut self, id: &str, fields: &[&Field], container: &ContainerAttrs) -> Value {
+        if fields.is_empty() {
+            return Value::Null;
+        }
+        if fields[0].ident().parse::<usize>().is_ok() {
+            let items = fields
+                .iter()
+                .map(|f| self.field_value(id, f))
+                .collect::<Vec<Value>>();
+            return match items.as_slice() {
+                [newtype] => newtype.clone(),
+                _ => Value::Array(items),
+            };
+        }
+        let mut object = Map::new();
+        for f in fields {
+            let value = self.field_value(id, f);
+            if f.serde.flatten {
+                if let Value::Object(flattened) = value {
+                    object.extend(flattened);
+                }
+                continue;
+            }
+            if let Some(key) = f.json_key(container, true) {
+                object.insert(key, value);
+            }
+        }
+        Value::Object(object)
+    }
+
+    /// Value of a field. Integers with a custom serializer are strings.
+    fn field_value(&mut self, id: &str, f: &Field) -> Value {
+        if let Some(with) = &f.serde.with.ser {
+            return if is_integer(&f.ty) {
+                json!("0")
+            } else {
+                json!(format!("<custom format: {}>", with))
+            };
+        }
+        match syn::parse_str::<Type>(&f.ty) {
+            Ok(ty) => {
+                let resolve = schema::field_resolver(self.collection, id, f);
+                self.type_value(&ty, &resolve)
+            }
+            Err(_) => json!(format!("<unparsed type {}>", f.ty)),
+        }
+    }
+
+    /// Value of a Rust type. `resolve` finds the entry of a type name.
+    fn type_value(&mut self, ty: &Type, resolve: &dyn Fn(&str) -> Option<String>) -> Value {
+        match shape(ty) {
+            Shape::Optional(t) => self.type_value(t, resolve),
+            Shape::Array { item, .. } => json!([self.type_value(item, resolve)]),
+            Shape::Map(value) => json!({ "key": self.type_value(value, resolve) }),
+            Shape::Tuple(elems) => {
+                Value::Array(elems.iter().map(|e| self.type_value(e, resolve)).collect())
+            }
+            Shape::Named(name) => {
+                if let Some(id) = resolve(&name) {
+                    self.sample(&id)
+                } else if let Some(json) = schema::json_type(&name) {
+                    placeholder(json)
+                } else {
+                    json!(format!("<unresolved type {}>", name))
+                }
+            }
+            Shape::Unknown => Value::Null,
+        }
+    }
+
+    /// The first serialized variant, following the enum tagging
+    fn enum_value(&mut self, id: &str, entry: &Entry) -> Value {
+        let tagging = entry.serde.tagging();
+        let variants = entry.get_variants();
+        let (variant, name) = match variants
+            .iter()
+            .find_map(|v| v.json_name(&entry.serde, true).map(|name| (v, name)))
+        {
+            Some(found) => found,
+            None => return json!("<no serialized variant>"),
+        };
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant() == Some(variant.name.as_str()) && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        let payload = match variant.kind {
+            VariantKind::Unit => None,
+            _ => Some(self.fields_value(id, &fields, &entry.serde)),
+        };
+        match (tagging, payload) {
+            (Tagging::External, None) => json!(name),
+            (Tagging::External, Some(payload)) => json!({ name: payload }),
+            (Tagging::Internal(tag), payload) => {
+                let mut object = Map::new();
+                object.insert(tag, json!(name));
+                match payload {
+                    Some(Value::Object(payload)) => object.extend(payload),
+                    Some(Value::Null) | None => {}
+                    Some(payload) => {
+                        return json!(format!(
+                            "<{}: internally tagged variant of a non-object {}>",
+                            variant.name, payload
+                        ))
+                    }
+                }
+                Value::Object(object)
+            }
+            (Tagging::Adjacent(tag, _), None) => json!({ tag: name }),
+            (Tagging::Adjacent(tag, content), Some(payload)) => {
+                json!({ tag: name, content: payload })
+            }
+            (Tagging::Untagged, payload) => payload.unwrap_or(Value::Null),
+        }
+    }
+}
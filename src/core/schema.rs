@@ -0,0 +1,573 @@
+use crate::core::attrs::{ContainerAttrs, Tagging};
+use crate::core::db::{Collection, Color, DataType, Entry, Field, Variant, VariantKind};
+use crate::core::op::generic_types;
+
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ops::Deref;
+use syn::Type;
+
+/// JSON Schema dialect of the generated documents
+pub const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// JSON types of the Rust primitives and the std types that serialize as one
+pub(crate) const PRIMITIVES: &[(&str, &str)] = &[
+    ("bool", "boolean"),
+    ("i8", "integer"),
+    ("i16", "integer"),
+    ("i32", "integer"),
+    ("i64", "integer"),
+    ("i128", "integer"),
+    ("isize", "integer"),
+    ("u8", "integer"),
+    ("u16", "integer"),
+    ("u32", "integer"),
+    ("u64", "integer"),
+    ("u128", "integer"),
+    ("usize", "integer"),
+    ("f32", "number"),
+    ("f64", "number"),
+    ("char", "string"),
+    ("str", "string"),
+    ("String", "string"),
+    ("PathBuf", "string"),
+    ("Path", "string"),
+];
+
+/// Types that serialize as their content
+pub(crate) const POINTERS: &[&str] = &["Box", "Rc", "Arc", "Cow"];
+
+/// Types that serialize as a JSON array
+pub(crate) const SEQUENCES: &[&str] = &["Vec", "VecDeque", "LinkedList", "BinaryHeap"];
+
+/// Types that serialize as a JSON array of unique items
+pub(crate) const SETS: &[&str] = &["BTreeSet", "HashSet", "IndexSet"];
+
+/// Types that serialize as a JSON object
+pub(crate) const MAPS: &[&str] = &["BTreeMap", "HashMap", "IndexMap"];
+
+/// JSON type of a primitive type
+pub(crate) fn json_type(name: &str) -> Option<&'static str> {
+    PRIMITIVES
+        .iter()
+        .find(|(rust, _)| *rust == name)
+        .map(|(_, json)| *json)
+}
+
+/// JSON schema of a primitive type
+fn primitive(name: &str) -> Option<Value> {
+    json_type(name).map(|json| json!({ "type": json }))
+}
+
+/// Integer types, that custom serializers usually write as strings
+pub(crate) fn is_integer(ty: &str) -> bool {
+    let ty = ty
+        .strip_prefix("Option<")
+        .and_then(|t| t.strip_suffix('>'))
+        .unwrap_or(ty);
+    json_type(ty) == Some("integer")
+}
+
+/// Names of the entries in generated code: the type name, qualified with the module when it is not unique
+/// (`block/parts::Header` -> `BlockPartsHeader`)
+pub(crate) fn type_names(collection: &Collection) -> BTreeMap<String, String> {
+    let mut counts = BTreeMap::<&str, usize>::new();
+    for (id, _) in collection.entries() {
+        *counts.entry(id.rsplit("::").next().unwrap()).or_default() += 1;
+    }
+    collection
+        .entries()
+        .map(|(id, _)| {
+            let name = id.rsplit("::").next().unwrap();
+            let qualified = if counts[name] > 1 {
+                id.split(['/', ':', '-', '_'])
+                    .filter(|s| !s.is_empty())
+                    .map(|s| {
+                        let mut chars = s.chars();
+                        chars.next().map_or(String::new(), |c| {
+                            c.to_ascii_uppercase().to_string() + chars.as_str()
+                        })
+                    })
+                    .collect::<String>()
+            } else {
+                name.to_string()
+            };
+            (id.clone(), qualified)
+        })
+        .collect()
+}
+
+/// JSON shape of a Rust type, through the pointers and references. The generators render each shape in their
+/// language and recurse into the inner types.
+pub(crate) enum Shape<'t> {
+    /// `Option<T>`: the content or `null`
+    Optional(&'t Type),
+    /// Sequences, sets, slices and arrays: a JSON array, of a fixed length for arrays
+    Array {
+        item: &'t Type,
+        unique: bool,
+        len: Option<usize>,
+    },
+    /// Maps: a JSON object of the values
+    Map(&'t Type),
+    /// Tuples: a JSON array of the elements, `()` is `null`
+    Tuple(Vec<&'t Type>),
+    /// Type name of an entry or a primitive
+    Named(String),
+    /// Type without a JSON shape, or generic type without its arguments
+    Unknown,
+}
+
+/// JSON shape of a Rust type
+pub(crate) fn shape(ty: &Type) -> Shape<'_> {
+    match ty {
+        Type::Path(p) => {
+            let last = match p.path.segments.last() {
+                Some(last) => last,
+                None => return Shape::Unknown,
+            };
+            let name = last.ident.to_string();
+            let args = generic_types(last);
+            let arg = |i: usize| args.get(i).copied();
+            let shape = if name == "Option" {
+                arg(0).map(Shape::Optional)
+            } else if POINTERS.contains(&name.as_str()) {
+                return arg(0).map_or(Shape::Unknown, shape);
+            } else if SEQUENCES.contains(&name.as_str()) || SETS.contains(&name.as_str()) {
+                arg(0).map(|item| Shape::Array {
+                    item,
+                    unique: SETS.contains(&name.as_str()),
+                    len: None,
+                })
+            } else if MAPS.contains(&name.as_str()) {
+                arg(1).map(Shape::Map)
+            } else {
+                Some(Shape::Named(name))
+            };
+            shape.unwrap_or(Shape::Unknown)
+        }
+        Type::Reference(r) => shape(r.elem.deref()),
+        Type::Group(g) => shape(g.elem.deref()),
+        Type::Paren(p) => shape(p.elem.deref()),
+        Type::Slice(s) => Shape::Array {
+            item: s.elem.deref(),
+            unique: false,
+            len: None,
+        },
+        Type::Array(a) => Shape::Array {
+            item: a.elem.deref(),
+            unique: false,
+            len: match &a.len {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(n),
+                    ..
+                }) => n.base10_parse::<usize>().ok(),
+                _ => None,
+            },
+        },
+        Type::Tuple(t) => Shape::Tuple(t.elems.iter().collect()),
+        _ => Shape::Unknown,
+    }
+}
+
+/// Entry of a type name in the type of a field: a target of the field's edges with the same name, or the only
+/// target of the field for imports like `Header as PartSetHeader`.
+pub(crate) fn field_resolver(
+    collection: &Collection,
+    id: &str,
+    f: &Field,
+) -> impl Fn(&str) -> Option<String> {
+    let targets = collection
+        .edges()
+        .iter()
+        .filter(|e| e.from == id && e.fields.contains(&f.name))
+        .map(|e| e.to.clone())
+        .collect::<Vec<String>>();
+    move |name: &str| match targets
+        .iter()
+        .find(|to| to.rsplit("::").next() == Some(name))
+    {
+        Some(to) => Some(to.clone()),
+        None if targets.len() == 1 && json_type(name).is_none() => Some(targets[0].clone()),
+        None => None,
+    }
+}
+
+/// Entry of a proxy type name: the only entry with that name
+pub(crate) fn proxy_resolver(collection: &Collection) -> impl Fn(&str) -> Option<String> + '_ {
+    move |name: &str| {
+        let found = collection
+            .entries()
+            .filter(|(id, _)| id.rsplit("::").next() == Some(name))
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<String>>();
+        match found.as_slice() {
+            [id] => Some(id.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// `$ref` of an entry in the `$defs` of the document (JSON pointer escaped)
+fn reference(id: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", id.replace('~', "~0").replace('/', "~1")) })
+}
+
+/// JSON Schema generator for one direction: the JSON written by `Serialize` or accepted by `Deserialize`
+pub struct Generator<'a> {
+    collection: &'a Collection,
+    serialize: bool,
+    /// Entries referenced by the generated schemas
+    refs: BTreeSet<String>,
+}
+
+impl<'a> Generator<'a> {
+    pub fn new(collection: &'a Collection, serialize: bool) -> Self {
+        Self {
+            collection,
+            serialize,
+            refs: BTreeSet::new(),
+        }
+    }
+
+    /// Document with the schemas of the roots and every type they reference in `$defs`.
+    /// A single root is also the schema of the document.
+    pub fn document(&mut self, roots: &[String]) -> Value {
+        let mut defs = BTreeMap::<String, Value>::new();
+        let mut queue = roots.iter().cloned().collect::<VecDeque<String>>();
+        while let Some(id) = queue.pop_front() {
+            if defs.contains_key(&id) {
+                continue;
+            }
+            defs.insert(id.clone(), self.entry_schema(&id));
+            queue.extend(std::mem::take(&mut self.refs));
+        }
+        let mut document = Map::new();
+        document.insert("$schema".to_string(), json!(DIALECT));
+        if let [root] = roots {
+            document.insert("$ref".to_string(), reference(root)["$ref"].clone());
+        }
+        document.insert("$defs".to_string(), json!(defs));
+        Value::Object(document)
+    }
+
+    /// The serializable entries (for the direction) of the collection
+    pub fn roots(collection: &Collection, serialize: bool) -> Vec<String> {
+        collection
+            .entries()
+            .filter(|(_, e)| e.public && e.get_color() != Color::White)
+            .filter(|(_, e)| {
+                if serialize {
+                    e.serialize || e.serializer || e.serde_into
+                } else {
+                    e.deserialize || e.deserializer || e.serde_from
+                }
+            })
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    fn entry_schema(&mut self, id: &str) -> Value {
+        let entry = match self.collection.get(id) {
+            Some(entry) => entry,
+            None => return json!({ "$comment": format!("unknown type {}", id) }),
+        };
+        let mut schema = self.shape(id, entry);
+        if let Value::Object(map) = &mut schema {
+            map.insert(
+                "title".to_string(),
+                json!(id.rsplit("::").next().unwrap_or(id)),
+            );
+            if let Some(doc) = entry.doc() {
+                map.insert("description".to_string(), json!(doc));
+            }
+        }
+        schema
+    }
+
+    /// Schema of an entry without the annotations
+    fn shape(&mut self, id: &str, entry: &Entry) -> Value {
+        let (proxy, custom, derive) = if self.serialize {
+            (&entry.serde.into, entry.serializer, entry.serialize)
+        } else {
+            (&entry.serde.from, entry.deserializer, entry.deserialize)
+        };
+        if let Some(proxy) = proxy {
+            return self.proxy_schema(proxy);
+        }
+        if custom || !derive {
+            return json!({
+                "$comment": format!(
+                    "custom {} implementation",
+                    if self.serialize { "Serialize" } else { "Deserialize" }
+                )
+            });
+        }
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant().is_none())
+            .collect::<Vec<&Field>>();
+        match entry.r#type {
+            DataType::Struct if entry.serde.transparent => {
+                match fields.iter().find(|f| !self.skipped(f)) {
+                    Some(f) => self.field_schema(id, f),
+                    None => json!({ "type": "null" }),
+                }
+            }
+            DataType::Struct => self.fields_schema(id, &fields, &entry.serde),
+            DataType::Enum => self.enum_schema(id, entry),
+            DataType::Unknown => json!({ "$comment": "type not defined in the source" }),
+        }
+    }
+
+    /// `from`/`try_from`/`into` proxy type: an entry or a primitive
+    fn proxy_schema(&mut self, proxy: &str) -> Value {
+        let ty = match syn::parse_str::<Type>(proxy) {
+            Ok(ty) => ty,
+            Err(_) => return json!({ "$comment": format!("proxy type {}", proxy) }),
+        };
+        let resolve = proxy_resolver(self.collection);
+        self.type_schema(&ty, &resolve)
+    }
+
+    fn skipped(&self, f: &Field) -> bool {
+        if self.serialize {
+            f.serde.skip_serializing
+        } else {
+            f.serde.skip_deserializing
+        }
+    }
+
+    fn is_required(&self, f: &Field, container: &ContainerAttrs) -> bool {
+        if self.serialize {
+            f.serde.skip_serializing_if.is_none()
+        } else {
+            !f.is_optional(container)
+        }
+    }
+
+    /// Object of the named fields, array of the tuple fields, `null` for unit structs
+    fn fields_schema(&mut self, id: &str, fields: &[&Field], container: &ContainerAttrs) -> Value {
+        let fields = fields
+            .iter()
+            .filter(|f| !self.skipped(f))
+            .copied()
+            .collect::<Vec<&Field>>();
+        if fields.is_empty() {
+            return json!({ "type": "null" });
+        }
+        if fields[0].ident().parse::<usize>().is_ok() {
+            let items = fields
+                .iter()
+                .map(|f| self.field_schema(id, f))
+                .collect::<Vec<Value>>();
+            return match items.as_slice() {
+                [newtype] => newtype.clone(),
+                _ => json!({
+                    "type": "array",
+                    "prefixItems": items,
+                    "minItems": items.len(),
+                    "maxItems": items.len(),
+                }),
+            };
+        }
+        let mut properties = Map::new();
+        let mut required = Vec::<String>::new();
+        let mut flattened = Vec::<Value>::new();
+        let mut additional = if container.deny_unknown_fields {
+            Some(json!(false))
+        } else {
+            None
+        };
+        for f in fields {
+            let schema = self.field_schema(id, f);
+            if f.serde.flatten {
+                // A flattened map takes the unknown keys, a flattened struct adds its properties
+                match schema.get("additionalProperties") {
+                    Some(values) if schema.get("type") == Some(&json!("object")) => {
+                        additional = Some(values.clone())
+                    }
+                    _ => flattened.push(schema),
+                }
+                continue;
+            }
+            let key = match f.json_key(container, self.serialize) {
+                Some(key) => key,
+                None => continue,
+            };
+            if self.is_required(f, container) {
+                required.push(key.clone());
+            }
+            properties.insert(key, schema);
+        }
+        let mut object = Map::new();
+        object.insert("type".to_string(), json!("object"));
+        object.insert("properties".to_string(), Value::Object(properties));
+        if !required.is_empty() {
+            object.insert("required".to_string(), json!(required));
+        }
+        if let Some(additional) = additional {
+            if flattened.is_empty() {
+                object.insert("additionalProperties".to_string(), additional);
+            }
+        }
+        if flattened.is_empty() {
+            Value::Object(object)
+        } else {
+            flattened.insert(0, Value::Object(object));
+            json!({ "allOf": flattened })
+        }
+    }
+
+    /// Schema of a field with its doc as description
+    fn field_schema(&mut self, id: &str, f: &Field) -> Value {
+        let with = if self.serialize {
+            &f.serde.with.ser
+        } else {
+            &f.serde.with.de
+        };
+        let mut schema = match (with, syn::parse_str::<Type>(&f.ty)) {
+            (Some(with), _) => json!({ "$comment": format!("custom format: {}", with) }),
+            (None, Ok(ty)) => {
+                let resolve = field_resolver(self.collection, id, f);
+                self.type_schema(&ty, &resolve)
+            }
+            (None, Err(_)) => json!({ "$comment": format!("unparsed type {}", f.ty) }),
+        };
+        if let (Some(doc), Value::Object(map)) = (&f.doc, &mut schema) {
+            map.insert("description".to_string(), json!(doc));
+        }
+        schema
+    }
+
+    /// Schema of a Rust type. `resolve` finds the entry of a type name.
+    fn type_schema(&mut self, ty: &Type, resolve: &dyn Fn(&str) -> Option<String>) -> Value {
+        match shape(ty) {
+            Shape::Optional(t) => {
+                json!({ "anyOf": [self.type_schema(t, resolve), { "type": "null" }] })
+            }
+            Shape::Array { item, unique, len } => {
+                let mut schema =
+                    json!({ "type": "array", "items": self.type_schema(item, resolve) });
+                if unique {
+                    schema["uniqueItems"] = json!(true);
+                }
+                if let Some(n) = len {
+                    schema["minItems"] = json!(n);
+                    schema["maxItems"] = json!(n);
+                }
+                schema
+            }
+            Shape::Map(value) => {
+                json!({ "type": "object", "additionalProperties": self.type_schema(value, resolve) })
+            }
+            Shape::Tuple(elems) if elems.is_empty() => json!({ "type": "null" }),
+            Shape::Tuple(elems) => {
+                let items = elems
+                    .iter()
+                    .map(|e| self.type_schema(e, resolve))
+                    .collect::<Vec<Value>>();
+                json!({
+                    "type": "array",
+                    "prefixItems": items,
+                    "minItems": items.len(),
+                    "maxItems": items.len(),
+                })
+            }
+            Shape::Named(name) => {
+                if let Some(id) = resolve(&name) {
+                    let schema = reference(&id);
+                    self.refs.insert(id);
+                    schema
+                } else if let Some(schema) = primitive(&name) {
+                    schema
+                } else {
+                    json!({ "$comment": format!("unresolved type {}", name) })
+                }
+            }
+            Shape::Unknown => json!({}),
+        }
+    }
+
+    /// One schema per variant, combined with `oneOf` (`anyOf` for untagged enums)
+    fn enum_schema(&mut self, id: &str, entry: &Entry) -> Value {
+        let tagging = entry.serde.tagging();
+        let mut variants = Vec::<Value>::new();
+        for variant in entry.get_variants() {
+            let name = match variant.json_name(&entry.serde, self.serialize) {
+                Some(name) => name,
+                None => continue,
+            };
+            // Unknown tags are read into the `#[serde(other)]` variant
+            let tag = if variant.serde.other && !self.serialize {
+                json!({ "type": "string" })
+            } else {
+                json!({ "const": name })
+            };
+            let payload = self.payload_schema(id, entry, &variant);
+            let mut schema = match (&tagging, payload) {
+                (Tagging::External, None) => tag,
+                (Tagging::External, Some(payload)) => json!({
+                    "type": "object",
+                    "properties": { name.clone(): payload },
+                    "required": [name],
+                    "additionalProperties": false,
+                }),
+                (Tagging::Internal(key), None) | (Tagging::Adjacent(key, _), None) => json!({
+                    "type": "object",
+                    "properties": { key.clone(): tag },
+                    "required": [key],
+                }),
+                (Tagging::Internal(key), Some(mut payload)) => {
+                    if variant.kind == VariantKind::Struct && payload.get("properties").is_some() {
+                        payload["properties"][key.as_str()] = tag;
+                        let mut required = payload
+                            .get("required")
+                            .cloned()
+                            .unwrap_or_else(|| json!([]));
+                        if let Value::Array(required) = &mut required {
+                            required.insert(0, json!(key));
+                        }
+                        payload["required"] = required;
+                        payload
+                    } else {
+                        json!({ "allOf": [
+                            { "type": "object", "properties": { key.clone(): tag }, "required": [key] },
+                            payload,
+                        ] })
+                    }
+                }
+                (Tagging::Adjacent(key, content), Some(payload)) => json!({
+                    "type": "object",
+                    "properties": { key.clone(): tag, content.clone(): payload },
+                    "required": [key, content],
+                }),
+                (Tagging::Untagged, None) => json!({ "type": "null" }),
+                (Tagging::Untagged, Some(payload)) => payload,
+            };
+            if let (Some(doc), Value::Object(map)) = (&variant.doc, &mut schema) {
+                map.insert("description".to_string(), json!(doc));
+            }
+            variants.push(schema);
+        }
+        if tagging == Tagging::Untagged {
+            json!({ "anyOf": variants })
+        } else {
+            json!({ "oneOf": variants })
+        }
+    }
+
+    /// Schema of the variant content, `None` for unit variants
+    fn payload_schema(&mut self, id: &str, entry: &Entry, variant: &Variant) -> Option<Value> {
+        if variant.kind == VariantKind::Unit {
+            return None;
+        }
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant() == Some(variant.name.as_str()))
+            .collect::<Vec<&Field>>();
+        Some(self.fields_schema(id, &fields, &entry.serde))
+    }
+}
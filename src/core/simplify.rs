@@ -0,0 +1,87 @@
+use crate::core::db::{Collection, Edge};
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Merge the edges between the same two entries into one. Edges that stand for several fields
+/// (for example the same type in several enum variants) are labeled with the number of fields.
+pub fn merge_parallel(collection: &mut Collection) {
+    let mut merged = BTreeMap::<(String, String), Edge>::new();
+    for e in collection.edges.drain(..) {
+        match merged.get_mut(&(e.from.clone(), e.to.clone())) {
+            Some(m) => {
+                m.solid |= e.solid;
+                for field in e.fields {
+                    if !m.fields.contains(&field) {
+                        m.fields.push(field);
+                    }
+                }
+            }
+            None => {
+                merged.insert((e.from.clone(), e.to.clone()), e);
+            }
+        }
+    }
+    collection.edges = merged
+        .into_values()
+        .map(|mut e| {
+            if e.fields.len() > 1 {
+                e.label = Some(format!("×{}", e.fields.len()));
+            }
+            e
+        })
+        .collect();
+}
+
+/// Hide the edges to leaf entries (without dependencies of their own) that are embedded by at least
+/// `min_fan_in` entries, like `Height`, `Hash` or `Time`.
+pub fn hide_leaves(collection: &mut Collection, min_fan_in: usize) {
+    let sources = collection
+        .edges
+        .iter()
+        .map(|e| e.from.as_str())
+        .collect::<BTreeSet<&str>>();
+    let mut fan_in = BTreeMap::<&str, BTreeSet<&str>>::new();
+    for e in &collection.edges {
+        fan_in.entry(&e.to).or_default().insert(&e.from);
+    }
+    let leaves = fan_in
+        .into_iter()
+        .filter(|(id, from)| !sources.contains(id) && from.len() >= min_fan_in)
+        .map(|(id, _)| id.to_string())
+        .collect::<BTreeSet<String>>();
+    collection.edges.retain(|e| !leaves.contains(&e.to));
+}
+
+/// Transitive reduction: remove the edges that are implied by a longer path.
+/// Reachability is kept, also in recursive types.
+pub fn reduce(collection: &mut Collection) {
+    let mut i = 0;
+    while i < collection.edges.len() {
+        let (from, to) = (&collection.edges[i].from, &collection.edges[i].to);
+        if from != to && reachable(&collection.edges, i, from, to) {
+            collection.edges.remove(i);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// `to` is reachable from `from` without the edge at `skip`
+fn reachable(edges: &[Edge], skip: usize, from: &str, to: &str) -> bool {
+    let mut seen = BTreeSet::<&str>::new();
+    let mut queue = VecDeque::from(vec![from]);
+    while let Some(current) = queue.pop_front() {
+        for (i, e) in edges.iter().enumerate() {
+            if i == skip || e.from != current {
+                continue;
+            }
+            if e.to == to {
+                return true;
+            }
+            if seen.insert(&e.to) {
+                queue.push_back(&e.to);
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,139 @@
+use crate::core::db::{Collection, Color, Edge};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Version of the snapshot file format. Increase it when the serialized collection changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    version: u32,
+    #[serde(flatten)]
+    collection: Collection,
+}
+
+/// Serialize the collection (entries, edges, diagnostics and sources) into a snapshot.
+pub fn save(collection: Collection) -> String {
+    serde_json::to_string_pretty(&Snapshot {
+        version: SNAPSHOT_VERSION,
+        collection,
+    })
+    .expect("Unable to serialize snapshot")
+}
+
+/// Load a snapshot file. Fails on unreadable files and unsupported versions.
+pub fn load(path: &Path) -> Result<Collection, String> {
+    let src = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read snapshot file {}: {}", path.display(), e))?;
+    let parse_error =
+        |e: serde_json::Error| format!("Unable to parse snapshot file {}: {}", path.display(), e);
+    let version = serde_json::from_str::<serde_json::Value>(&src)
+        .map_err(parse_error)?
+        .get("version")
+        .and_then(|v| v.as_u64());
+    if version != Some(SNAPSHOT_VERSION as u64) {
+        return Err(format!(
+            "unsupported snapshot version of {}: {:?}, expected: {}",
+            path.display(),
+            version,
+            SNAPSHOT_VERSION
+        ));
+    }
+    Ok(serde_json::from_str::<Snapshot>(&src)
+        .map_err(parse_error)?
+        .collection)
+}
+
+/// Prefix all ids of a single-source collection with its crate name: `tendermint::block/height::Height`
+fn qualify(mut collection: Collection) -> Result<Collection, String> {
+    if collection.sources.iter().any(|s| s.prefix.is_some()) {
+        return Ok(collection);
+    }
+    if collection.sources.len() != 1 {
+        return Err(
+            "can't qualify the ids of a collection with multiple unqualified sources".to_string(),
+        );
+    }
+    let prefix = collection.sources[0].crate_name.clone();
+    let q = |id: &str| format!("{}::{}", prefix, id);
+    collection.entries = collection
+        .entries
+        .into_iter()
+        .map(|(id, entry)| (q(&id), entry))
+        .collect();
+    for e in collection.edges.iter_mut() {
+        e.from = q(&e.from);
+        e.to = q(&e.to);
+    }
+    for d in collection.diagnostics.iter_mut() {
+        d.id = q(&d.id);
+    }
+    collection.sources[0].prefix = Some(prefix);
+    Ok(collection)
+}
+
+/// Combine collections of several crates or repositories into one graph with crate-qualified ids.
+/// Fields that couldn't be resolved in their own crate are linked to the other crates,
+/// if the field path starts with the crate name and the type name is unique in that crate.
+/// Fails if a crate is merged twice.
+pub fn merge(collections: Vec<Collection>) -> Result<Collection, String> {
+    let mut merged = Collection::default();
+    for collection in collections {
+        let collection = qualify(collection)?;
+        for source in collection.sources {
+            if merged.sources.iter().any(|s| s.prefix == source.prefix) {
+                return Err(format!(
+                    "crate {} is merged twice, rename it with --crate-name",
+                    source.prefix.unwrap_or_default()
+                ));
+            }
+            merged.sources.push(source);
+        }
+        merged.entries.extend(collection.entries);
+        merged.edges.extend(collection.edges);
+        merged.diagnostics.extend(collection.diagnostics);
+    }
+
+    // Type name -> ids, for every crate prefix (with `-` replaced by `_` as in Rust paths)
+    let mut index = BTreeMap::<(String, String), Vec<String>>::new();
+    for id in merged.entries.keys() {
+        if let Some((prefix, _)) = id.split_once("::") {
+            let name = id.rsplit("::").next().unwrap();
+            index
+                .entry((prefix.replace('-', "_"), name.to_string()))
+                .or_default()
+                .push(id.clone());
+        }
+    }
+    let mut linked = Vec::<usize>::new();
+    for (i, d) in merged.diagnostics.iter().enumerate() {
+        let field = match &d.field {
+            Some(f) => f,
+            None => continue,
+        };
+        let (krate, name) = match (field.split("::").next(), field.rsplit("::").next()) {
+            (Some(k), Some(n)) if k != n => (k.to_string(), n.to_string()),
+            _ => continue,
+        };
+        if let Some(targets) = index.get(&(krate, name)) {
+            if targets.len() == 1 {
+                let color = merged.entries[&d.id].get_color();
+                merged.edges.push(Edge {
+                    from: d.id.clone(),
+                    to: targets[0].clone(),
+                    solid: color == Color::Green || color == Color::GreenGradient,
+                    fields: merged.entries[&d.id].members_with_ident(field),
+                    label: None,
+                });
+                linked.push(i);
+            }
+        }
+    }
+    for i in linked.into_iter().rev() {
+        merged.diagnostics.remove(i);
+    }
+    Ok(merged)
+}
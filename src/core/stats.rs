@@ -0,0 +1,331 @@
+use crate::core::db::{Collection, Color, Edge};
+use crate::core::graph::{Direction, Graph};
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::str::FromStr;
+
+/// Column to sort the stats table by. The numbers are sorted in descending order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Sort {
+    Id,
+    FanIn,
+    FanOut,
+    Depth,
+    Fields,
+    Betweenness,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "id" => Ok(Sort::Id),
+            "fan-in" => Ok(Sort::FanIn),
+            "fan-out" => Ok(Sort::FanOut),
+            "depth" => Ok(Sort::Depth),
+            "fields" => Ok(Sort::Fields),
+            "betweenness" => Ok(Sort::Betweenness),
+            _ => Err(format!("unknown column: {}", s)),
+        }
+    }
+}
+
+/// Metrics of a single entry
+#[derive(Debug, Serialize)]
+pub struct TypeStats {
+    pub id: String,
+    pub color: Color,
+    /// struct, enum or unknown
+    pub kind: String,
+    /// Number of entries that embed this one
+    pub fan_in: usize,
+    /// Number of entries used by the fields
+    pub fan_out: usize,
+    /// Maximum nesting depth of the serialized form, `None` if it is unbounded (recursive types)
+    pub depth: Option<usize>,
+    /// Number of members that carry data: the fields and the fields of the variants, not the unit variants
+    pub fields: usize,
+    /// Normalized betweenness centrality: the share of shortest paths between other entries that go through this one
+    pub betweenness: f64,
+}
+
+/// Number of entries and fields
+#[derive(Debug, Default, Serialize)]
+pub struct Total {
+    pub types: usize,
+    pub fields: usize,
+}
+
+/// Metrics of the public entries with crate-wide totals
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub types: Vec<TypeStats>,
+    pub colors: BTreeMap<Color, usize>,
+    pub kinds: BTreeMap<String, Total>,
+    pub total: Total,
+}
+
+impl Stats {
+    /// Compute the metrics of the public entries. With `only_json`, only the serializable entries are counted.
+    pub fn new(collection: &Collection, only_json: bool) -> Self {
+        let ids = collection
+            .entries()
+            .filter(|(_, e)| e.public && !(only_json && e.get_color() == Color::White))
+            .map(|(id, _)| id.as_str())
+            .collect::<BTreeSet<&str>>();
+        let view = collection.subset(&ids.iter().map(|id| id.to_string()).collect());
+        let graph = Graph::new(&view);
+        let betweenness = betweenness(&graph, &ids);
+        let mut depths = BTreeMap::<String, Option<usize>>::new();
+
+        let mut stats = Self {
+            types: Vec::new(),
+            colors: BTreeMap::new(),
+            kinds: BTreeMap::new(),
+            total: Total::default(),
+        };
+        for (id, entry) in view.entries() {
+            let count = |direction| {
+                graph
+                    .neighbors(id, direction)
+                    .into_iter()
+                    .map(|(_, other)| other)
+                    .collect::<BTreeSet<&str>>()
+                    .len()
+            };
+            let kind = format!("{:?}", entry.r#type).to_lowercase();
+            let fields = entry.members.iter().filter(|m| !m.ty.is_empty()).count();
+            *stats.colors.entry(entry.get_color()).or_default() += 1;
+            let total = stats.kinds.entry(kind.clone()).or_default();
+            total.types += 1;
+            total.fields += fields;
+            stats.total.types += 1;
+            stats.total.fields += fields;
+            stats.types.push(TypeStats {
+                id: id.clone(),
+                color: entry.get_color(),
+                kind,
+                fan_in: count(Direction::Upstream),
+                fan_out: count(Direction::Downstream),
+                depth: depth(&graph, &view, id, &mut depths, &mut BTreeSet::new()),
+                fields,
+                betweenness: betweenness[id.as_str()],
+            });
+        }
+        stats
+    }
+
+    /// Sort the types by a column
+    pub fn sort(&mut self, sort: Sort) {
+        self.types.sort_by(|a, b| {
+            match sort {
+                Sort::Id => a.id.cmp(&b.id),
+                Sort::FanIn => b.fan_in.cmp(&a.fan_in),
+                Sort::FanOut => b.fan_out.cmp(&a.fan_out),
+                // Recursive types (unbounded depth) first
+                Sort::Depth => b
+                    .depth
+                    .unwrap_or(usize::MAX)
+                    .cmp(&a.depth.unwrap_or(usize::MAX)),
+                Sort::Fields => b.fields.cmp(&a.fields),
+                Sort::Betweenness => b.betweenness.total_cmp(&a.betweenness),
+            }
+            .then_with(|| a.id.cmp(&b.id))
+        });
+    }
+
+    /// Rows of the table: the column names and the types
+    fn rows(&self) -> Vec<Vec<String>> {
+        let mut rows = vec![[
+            "id",
+            "color",
+            "kind",
+            "fan-in",
+            "fan-out",
+            "depth",
+            "fields",
+            "betweenness",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<String>>()];
+        for t in &self.types {
+            rows.push(vec![
+                t.id.clone(),
+                t.color.to_string(),
+                t.kind.clone(),
+                t.fan_in.to_string(),
+                t.fan_out.to_string(),
+                t.depth.map_or("recursive".to_string(), |d| d.to_string()),
+                t.fields.to_string(),
+                format!("{:.3}", t.betweenness),
+            ]);
+        }
+        rows
+    }
+
+    /// Aligned table followed by the totals
+    pub fn to_text(&self) -> String {
+        let rows = self.rows();
+        let mut widths = vec![0; rows[0].len()];
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(cell.len());
+            }
+        }
+        let mut lines = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(i, cell)| match i {
+                        0..=2 => format!("{:<width$}", cell, width = widths[i]),
+                        _ => format!("{:>width$}", cell, width = widths[i]),
+                    })
+                    .collect::<Vec<String>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+        lines.push("".to_string());
+        let summary = |total: &Total| {
+            format!(
+                "{}, {}",
+                plural(total.types, "type"),
+                plural(total.fields, "field")
+            )
+        };
+        lines.push(format!("Total: {}", summary(&self.total)));
+        for (kind, total) in &self.kinds {
+            lines.push(format!("  {}: {}", kind, summary(total)));
+        }
+        for (color, count) in &self.colors {
+            lines.push(format!("  {}: {}", color, plural(*count, "type")));
+        }
+        lines.join("\n")
+    }
+
+    /// Comma-separated table of the types, for spreadsheets
+    pub fn to_csv(&self) -> String {
+        self.rows()
+            .iter()
+            .map(|row| row.join(","))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// `1 type`, `2 types`
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+/// Nesting depth of the serialized form: a type without nested types is 1 deep, flattened fields don't add a level
+/// and skipped fields don't count. `None` if the type is or contains a recursive type.
+fn depth(
+    graph: &Graph,
+    collection: &Collection,
+    id: &str,
+    known: &mut BTreeMap<String, Option<usize>>,
+    visiting: &mut BTreeSet<String>,
+) -> Option<usize> {
+    if let Some(d) = known.get(id) {
+        return *d;
+    }
+    if !visiting.insert(id.to_string()) {
+        return None;
+    }
+    let mut result = Some(1);
+    for (e, next) in graph.neighbors(id, Direction::Downstream) {
+        let level = match nesting(collection, e) {
+            Some(level) => level,
+            None => continue,
+        };
+        result = match (result, depth(graph, collection, next, known, visiting)) {
+            (Some(a), Some(b)) => Some(a.max(b + level)),
+            _ => None,
+        };
+    }
+    visiting.remove(id);
+    known.insert(id.to_string(), result);
+    result
+}
+
+/// Levels added by the fields of an edge: 0 if all of them are flattened, `None` if all of them are skipped
+fn nesting(collection: &Collection, e: &Edge) -> Option<usize> {
+    let entry = collection.get(&e.from)?;
+    let fields = entry
+        .members
+        .iter()
+        .filter(|f| e.fields.contains(&f.name))
+        .filter(|f| !(f.serde.skip_serializing && f.serde.skip_deserializing))
+        .collect::<Vec<_>>();
+    if fields.is_empty() && !e.fields.is_empty() {
+        None
+    } else if !fields.is_empty() && fields.iter().all(|f| f.serde.flatten) {
+        Some(0)
+    } else {
+        Some(1)
+    }
+}
+
+/// Normalized betweenness centrality of the directed graph (Brandes)
+fn betweenness<'a>(graph: &Graph, ids: &BTreeSet<&'a str>) -> BTreeMap<&'a str, f64> {
+    let mut result = ids
+        .iter()
+        .map(|id| (*id, 0.0))
+        .collect::<BTreeMap<&str, f64>>();
+    for source in ids {
+        let mut stack = Vec::<&str>::new();
+        let mut predecessors = BTreeMap::<&str, Vec<&str>>::new();
+        let mut paths = BTreeMap::<&str, f64>::new();
+        let mut distance = BTreeMap::<&str, usize>::new();
+        paths.insert(source, 1.0);
+        distance.insert(source, 0);
+        let mut queue = VecDeque::from(vec![*source]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            let targets = graph
+                .neighbors(v, Direction::Downstream)
+                .into_iter()
+                .map(|(_, w)| w)
+                .collect::<BTreeSet<&str>>();
+            for w in targets {
+                if !distance.contains_key(w) {
+                    distance.insert(w, distance[v] + 1);
+                    queue.push_back(w);
+                }
+                if distance[w] == distance[v] + 1 {
+                    *paths.entry(w).or_default() += paths[v];
+                    predecessors.entry(w).or_default().push(v);
+                }
+            }
+        }
+        let mut dependency = BTreeMap::<&str, f64>::new();
+        while let Some(w) = stack.pop() {
+            for v in predecessors.get(w).into_iter().flatten() {
+                let share = paths[v] / paths[w] * (1.0 + dependency.get(w).unwrap_or(&0.0));
+                *dependency.entry(v).or_default() += share;
+            }
+            if w != *source {
+                if let Some(b) = result.get_mut(w) {
+                    *b += dependency.get(w).unwrap_or(&0.0);
+                }
+            }
+        }
+    }
+    let n = ids.len() as f64;
+    if n > 2.0 {
+        for b in result.values_mut() {
+            *b /= (n - 1.0) * (n - 2.0);
+        }
+    }
+    result
+}
@@ -0,0 +1,428 @@
+use crate::core::consts;
+use crate::core::db::{Color, DataType, Encoding};
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Fill and stroke color of a node style
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Swatch {
+    pub fill: String,
+    pub stroke: String,
+}
+
+impl Swatch {
+    fn new(fill: &str, stroke: &str) -> Self {
+        Self {
+            fill: fill.to_string(),
+            stroke: stroke.to_string(),
+        }
+    }
+}
+
+/// Optional elements of a type diagram
+#[derive(Debug, Default)]
+pub struct Extras {
+    /// "+k more" nodes of a focused view
+    pub stubs: bool,
+    /// Edges of recursive types
+    pub cycles: bool,
+    /// Edge label nodes
+    pub labels: bool,
+}
+
+/// draw.io CSV import configuration. Every field is optional in the template file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Template {
+    pub title: String,
+    pub namespace: String,
+    pub layout: String,
+    pub nodespacing: u32,
+    pub levelspacing: u32,
+    pub edgespacing: u32,
+    pub padding: u32,
+    /// Node label, `%column%` placeholders are replaced by draw.io
+    pub label: String,
+    /// Swatches keyed by the base color name: red, green, blue, yellow, white, legend and stub
+    pub palette: BTreeMap<String, Swatch>,
+    /// Second color of the gradient styles
+    pub gradient: String,
+    /// Connector style of serialization dependencies
+    pub solid_edge: String,
+    /// Connector style of non-serialization dependencies
+    pub dashed_edge: String,
+    /// List the fields in the nodes (`field: Type` lines with the serde keys and markers)
+    /// instead of the dependencies
+    pub class_labels: bool,
+    /// Link of the nodes to their definition. Placeholders: `{rev}` (commit), `{path}` (path in the git
+    /// repository), `{file}` (local path), `{line}` and `{column}`.
+    pub source_url: String,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        let mut palette = BTreeMap::new();
+        palette.insert("red".to_string(), Swatch::new("#f8cecc", "#b85450"));
+        palette.insert("green".to_string(), Swatch::new("#d5e8d4", "#82b366"));
+        palette.insert("blue".to_string(), Swatch::new("#dae8fc", "#6c8ebf"));
+        palette.insert("yellow".to_string(), Swatch::new("#fff2cc", "#d6b656"));
+        palette.insert("white".to_string(), Swatch::new("#ffffff", "#000000"));
+        palette.insert("legend".to_string(), Swatch::new("#d0cee2", "#56517e"));
+        palette.insert("stub".to_string(), Swatch::new("#f5f5f5", "#666666"));
+        Self {
+            title: consts::DEFAULT_TITLE.to_string(),
+            namespace: consts::DEFAULT_NAMESPACE.to_string(),
+            layout: "horizontalflow".to_string(),
+            nodespacing: 60,
+            levelspacing: 60,
+            edgespacing: 60,
+            padding: 10,
+            label: consts::DEFAULT_LABEL.to_string(),
+            palette,
+            gradient: "#ffffff".to_string(),
+            solid_edge: "curved=1;endArrow=blockThin;endFill=1;".to_string(),
+            dashed_edge: "curved=1;endArrow=blockThin;endFill=1;dashed=1;dashPattern=1 5;"
+                .to_string(),
+            class_labels: false,
+            source_url: consts::DEFAULT_SOURCE_URL.to_string(),
+        }
+    }
+}
+
+impl Template {
+    /// Load a TOML template file. Missing keys and palette entries fall back to the defaults.
+    pub fn from_file(path: &Path) -> Self {
+        let src = fs::read_to_string(path).expect("Unable to read template file");
+        let mut template: Template = toml::from_str(&src).expect("Unable to parse template file");
+        for (name, swatch) in Template::default().palette {
+            template.palette.entry(name).or_insert(swatch);
+        }
+        template
+    }
+
+    fn swatch(&self, name: &str) -> &Swatch {
+        self.palette
+            .get(name)
+            .unwrap_or_else(|| panic!("missing palette entry: {}", name))
+    }
+
+    /// draw.io style of a node with the given color and border
+    fn style(&self, color: Color, encoding: Encoding) -> String {
+        let swatch = self.swatch(color.base());
+        let mut style = format!(
+            "shape=%shape%;rounded=1;html=1;fillColor={};strokeColor={};strokeWidth={}",
+            swatch.fill,
+            swatch.stroke,
+            if encoding == Encoding::Both { 4 } else { 2 }
+        );
+        if encoding == Encoding::Proto {
+            style.push_str(";dashed=1;dashPattern=8 4");
+        }
+        if color.is_gradient() {
+            style.push_str(format!(";gradientColor={}", self.gradient).as_str());
+        }
+        if self.class_labels {
+            style.push_str(";align=left;spacingLeft=8");
+        }
+        style
+    }
+
+    /// The legend row: one line for every color, border and shape that appears in the output, then the `notes`.
+    /// The borders are explained when there are protobuf types.
+    fn legend(
+        &self,
+        colors: &[Color],
+        encodings: &[Encoding],
+        types: &[DataType],
+        notes: &[&str],
+    ) -> String {
+        let mut lines = Vec::<String>::new();
+        let mut bases = Vec::<&str>::new();
+        for color in colors {
+            if bases.contains(&color.base()) {
+                continue;
+            }
+            bases.push(color.base());
+            lines.push(format!(
+                "<b style=\"color:{};\">{}:</b> {}",
+                self.swatch(color.base()).fill,
+                color.title(),
+                color.description()
+            ));
+        }
+        if colors.iter().any(|c| c.is_gradient()) {
+            lines.push("".to_string());
+            lines.push("Gradient color: asymmetric serialization".to_string());
+        }
+        if encodings
+            .iter()
+            .any(|e| matches!(e, Encoding::Proto | Encoding::Both))
+        {
+            lines.push("".to_string());
+            // The white nodes keep the default border and are not explained
+            let mut borders = encodings
+                .iter()
+                .filter_map(Encoding::description)
+                .collect::<Vec<&str>>();
+            borders.dedup();
+            lines.extend(borders.iter().map(|b| b.to_string()));
+        }
+        if !types.is_empty() {
+            lines.push("".to_string());
+        }
+        for t in types {
+            lines.push(t.description().to_string());
+        }
+        if !notes.is_empty() {
+            lines.push("".to_string());
+        }
+        for note in notes {
+            lines.push(note.to_string());
+        }
+        format!("<b>LEGEND<br><br>{}</b>", lines.join("<br>"))
+    }
+
+    /// Render the draw.io configuration lines, the CSV column header and the legend row.
+    /// `nodes` (colors and borders) and `types` are the values that appear in the output.
+    pub fn render(
+        &self,
+        nodes: &[(Color, Encoding)],
+        types: &[DataType],
+        extras: &Extras,
+    ) -> String {
+        let mut nodes = nodes.to_vec();
+        nodes.sort();
+        nodes.dedup();
+        let colors = nodes.iter().map(|(c, _)| *c).collect::<Vec<Color>>();
+        let mut encodings = nodes.iter().map(|(_, e)| *e).collect::<Vec<Encoding>>();
+        encodings.sort();
+        let mut types = types.to_vec();
+        types.sort();
+        types.dedup();
+
+        let mut styles = nodes
+            .iter()
+            .map(|(c, e)| (format!("{}{}", c, e.suffix()), self.style(*c, *e)))
+            .collect::<Vec<(String, String)>>();
+        if extras.stubs {
+            let swatch = self.swatch("stub");
+            styles.push((
+                "stub".to_string(),
+                format!(
+                    "shape=%shape%;rounded=1;dashed=1;html=1;fillColor={};strokeColor={};fontColor={}",
+                    swatch.fill, swatch.stroke, swatch.stroke
+                ),
+            ));
+        }
+        let connects = vec![
+            ("refs", self.solid_edge.clone()),
+            ("refs2", self.dashed_edge.clone()),
+            ("refs3", format!("{}strokeColor=none;", self.dashed_edge)),
+            (
+                "cycle",
+                format!(
+                    "{}strokeColor={};strokeWidth=3;",
+                    self.solid_edge,
+                    self.swatch("red").stroke
+                ),
+            ),
+        ];
+        if extras.labels {
+            styles.push((
+                "edge_label".to_string(),
+                "text;html=1;fontSize=10;fillColor=none;strokeColor=none;".to_string(),
+            ));
+        }
+        let mut notes = Vec::<&str>::new();
+        if extras.stubs {
+            notes.push("+k more: neighbors outside of the focus");
+        }
+        if extras.cycles {
+            notes.push("Red line: recursive types");
+        }
+        self.header(
+            &styles,
+            &connects,
+            "refs,refs2,cycle",
+            consts::CSV_COLUMNS,
+            &self.legend(&colors, &encodings, &types, &notes),
+            if extras.labels {
+                &[("edge_label", "<i>%deps%</i>")]
+            } else {
+                &[]
+            },
+        )
+    }
+
+    /// Render the header of a module diagram. `colors` are the node colors that appear in the output.
+    pub fn render_modules(&self, colors: &[Color]) -> String {
+        let mut colors = colors.to_vec();
+        colors.sort();
+        colors.dedup();
+
+        let styles = colors
+            .iter()
+            .map(|c| (c.to_string(), self.style(*c, Encoding::Json)))
+            .collect::<Vec<(String, String)>>();
+        let connects = vec![
+            ("light", format!("{}strokeWidth=1;", self.solid_edge)),
+            ("medium", format!("{}strokeWidth=3;", self.solid_edge)),
+            ("heavy", format!("{}strokeWidth=6;", self.solid_edge)),
+        ];
+        self.header(
+            &styles,
+            &connects,
+            "light,medium,heavy",
+            consts::MODULE_CSV_COLUMNS,
+            &self.legend(
+                &colors,
+                &[],
+                &[],
+                &[
+                    "Node color: the most frequent color of the module",
+                    "Line width: number of field references between the modules",
+                ],
+            ),
+            &[],
+        )
+    }
+
+    /// Render the header of a diff diagram. Added nodes and edges are green, removed ones are red,
+    /// changed nodes are yellow.
+    pub fn render_diff(&self) -> String {
+        let styles = [
+            ("added", "green"),
+            ("removed", "red"),
+            ("changed", "yellow"),
+            ("unchanged", "white"),
+        ]
+        .iter()
+        .map(|(name, base)| {
+            let swatch = self.swatch(base);
+            (
+                name.to_string(),
+                format!(
+                    "shape=%shape%;rounded=1;html=1;fillColor={};strokeColor={};strokeWidth=2",
+                    swatch.fill, swatch.stroke
+                ),
+            )
+        })
+        .collect::<Vec<(String, String)>>();
+        let connects = vec![
+            ("refs", self.solid_edge.clone()),
+            (
+                "added_refs",
+                format!(
+                    "{}strokeColor={};strokeWidth=2;",
+                    self.solid_edge,
+                    self.swatch("green").stroke
+                ),
+            ),
+            (
+                "removed_refs",
+                format!(
+                    "{}strokeColor={};strokeWidth=2;",
+                    self.dashed_edge,
+                    self.swatch("red").stroke
+                ),
+            ),
+        ];
+        let legend = [
+            ("green", "Added"),
+            ("red", "Removed"),
+            ("yellow", "Changed"),
+        ]
+        .iter()
+        .map(|(base, title)| {
+            format!(
+                "<b style=\"color:{};\">{}</b>",
+                self.swatch(base).fill,
+                title
+            )
+        })
+        .collect::<Vec<String>>();
+        self.header(
+            &styles,
+            &connects,
+            "refs,added_refs,removed_refs",
+            consts::DIFF_CSV_COLUMNS,
+            &format!("<b>LEGEND<br><br>{}</b>", legend.join("<br>")),
+            &[],
+        )
+    }
+
+    fn header(
+        &self,
+        styles: &[(String, String)],
+        connects: &[(&str, String)],
+        ignore: &str,
+        columns: &str,
+        legend_label: &str,
+        labels: &[(&str, &str)],
+    ) -> String {
+        let mut styles = styles
+            .iter()
+            .map(|(name, style)| format!("#            \"{}\": \"{}\"", name, style))
+            .collect::<Vec<String>>();
+        let legend = self.swatch("legend");
+        styles.push(format!(
+            "#            \"legend\": \"shape=%shape%;rounded=0;shadow=1;fontSize=16;align=left;whiteSpace=wrap;html=1;fillColor={};strokeWidth=2;strokeColor={};\"",
+            legend.fill, legend.stroke
+        ));
+
+        let mut result = String::new();
+        result.push_str(format!("## {} - draw.io CSV export\n", self.title).as_str());
+        result.push_str(format!("# label: {}\n", self.label).as_str());
+        result.push_str("# stylename: color\n");
+        if !labels.is_empty() {
+            // Rows with a named label use it instead of the default label
+            let labels = labels
+                .iter()
+                .map(|(name, label)| format!("{:?}: {:?}", name, label))
+                .collect::<Vec<String>>();
+            result.push_str("# labelname: color\n");
+            result.push_str(format!("# labels: {{{}}}\n", labels.join(", ")).as_str());
+        }
+        result.push_str("# styles: { \\\n");
+        result.push_str(styles.join(",\\\n").as_str());
+        result.push_str("\\\n# }\n");
+        for (column, style) in connects {
+            result.push_str(
+                format!(
+                    "# connect: {{\"from\":\"{}\", \"to\":\"name\", \"invert\":false, \"style\":\"{}\"}}\n",
+                    column, style
+                )
+                .as_str(),
+            );
+        }
+        if columns.split(',').any(|c| c == "url") {
+            result.push_str("# link: url\n");
+        }
+        result.push_str(format!("# namespace: {}\n", self.namespace).as_str());
+        result.push_str("# width: auto\n");
+        result.push_str("# height: auto\n");
+        result.push_str(format!("# padding: {}\n", self.padding).as_str());
+        result.push_str(format!("# ignore: {}\n", ignore).as_str());
+        result.push_str(format!("# nodespacing: {}\n", self.nodespacing).as_str());
+        result.push_str(format!("# levelspacing: {}\n", self.levelspacing).as_str());
+        result.push_str(format!("# edgespacing: {}\n", self.edgespacing).as_str());
+        result.push_str(format!("# layout: {}\n", self.layout).as_str());
+        result.push_str(columns);
+        result.push('\n');
+        let padding = ",".repeat(columns.matches(',').count() - 3);
+        result.push_str(
+            format!(
+                "{:?},rectangle,legend,{}{}",
+                legend_label,
+                padding,
+                consts::LEGEND_DEPS
+            )
+            .as_str(),
+        );
+        result
+    }
+}
@@ -0,0 +1,313 @@
+use crate::core::attrs::Tagging;
+use crate::core::db::{Collection, DataType, Entry, Field, Variant, VariantKind};
+use crate::core::op::is_tuple;
+use crate::core::schema::{self, is_integer, shape, Generator, Shape};
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use syn::Type;
+
+/// TypeScript declarations (`.d.ts`) of the JSON written by `Serialize`
+pub struct Declarations<'a> {
+    collection: &'a Collection,
+    /// TypeScript name of the entries
+    names: BTreeMap<String, String>,
+    /// Entries referenced by the generated types
+    refs: BTreeSet<String>,
+}
+
+/// TypeScript type of a JSON type
+fn ts_type(json: &str) -> &'static str {
+    match json {
+        "boolean" => "boolean",
+        "integer" | "number" => "number",
+        _ => "string",
+    }
+}
+
+/// Object key, quoted if it is not an identifier
+fn key(name: &str) -> String {
+    let mut chars = name.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if identifier {
+        name.to_string()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
+/// JSDoc comment
+fn jsdoc(doc: Option<&str>, indent: &str) -> String {
+    match doc {
+        None => String::new(),
+        Some(doc) => {
+            let lines = doc.lines().collect::<Vec<&str>>();
+            if let [line] = lines.as_slice() {
+                format!("{}/** {} */\n", indent, line)
+            } else {
+                let mut result = format!("{}/**\n", indent);
+                for line in lines {
+                    result.push_str(format!("{} * {}\n", indent, line).trim_end());
+                    result.push('\n');
+                }
+                result.push_str(format!("{} */\n", indent).as_str());
+                result
+            }
+        }
+    }
+}
+
+impl<'a> Declarations<'a> {
+    pub fn new(collection: &'a Collection) -> Self {
+        let names = schema::type_names(collection);
+        Self {
+            collection,
+            names,
+            refs: BTreeSet::new(),
+        }
+    }
+
+    /// Declarations of the serializable entries and the types they use
+    pub fn render(&mut self) -> String {
+        let mut done = BTreeMap::<String, String>::new();
+        let mut queue = Generator::roots(self.collection, true)
+            .into_iter()
+            .collect::<VecDeque<String>>();
+        while let Some(id) = queue.pop_front() {
+            if done.contains_key(&id) {
+                continue;
+            }
+            let declaration = self.declaration(&id);
+            done.insert(id, declaration);
+            queue.extend(std::mem::take(&mut self.refs));
+        }
+        done.into_values().collect::<Vec<String>>().join("\n")
+    }
+
+    fn declaration(&mut self, id: &str) -> String {
+        let entry = match self.collection.get(id) {
+            Some(entry) => entry,
+            None => return String::new(),
+        };
+        let name = self.names[id].clone();
+        let mut result = jsdoc(entry.doc(), "");
+        if let Some(proxy) = &entry.serde.into {
+            let resolve = schema::proxy_resolver(self.collection);
+            let ty = match syn::parse_str::<Type>(proxy) {
+                Ok(ty) => self.type_name(&ty, &resolve),
+                Err(_) => "unknown".to_string(),
+            };
+            result.push_str(format!("export type {} = {};\n", name, ty).as_str());
+            return result;
+        }
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant().is_none() && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        if entry.serializer || !entry.serialize {
+            // Custom `impl Serialize`: wrapped integers are usually written as strings
+            let ty = match fields.as_slice() {
+                [f] if is_integer(&f.ty) => "string /* custom Serialize */",
+                _ => "unknown /* custom Serialize */",
+            };
+            result.push_str(format!("export type {} = {};\n", name, ty).as_str());
+            return result;
+        }
+        match entry.r#type {
+            DataType::Struct if entry.serde.transparent || is_tuple(&fields) => {
+                let ty = self.tuple(id, &fields);
+                result.push_str(format!("export type {} = {};\n", name, ty).as_str());
+            }
+            DataType::Struct if fields.iter().any(|f| f.serde.flatten) => {
+                let ty = self.object(id, entry, &fields, "");
+                result.push_str(format!("export type {} = {};\n", name, ty).as_str());
+            }
+            DataType::Struct => {
+                let ty = self.object(id, entry, &fields, "");
+                result.push_str(format!("export interface {} {}\n", name, ty).as_str());
+            }
+            DataType::Enum => {
+                let ty = self.union(id, entry);
+                result.push_str(format!("export type {} =\n{};\n", name, ty).as_str());
+            }
+            DataType::Unknown => {
+                result.push_str(format!("export type {} = unknown;\n", name).as_str());
+            }
+        }
+        result
+    }
+
+    /// `[A, B]` of tuple fields, `A` of a newtype, `null` without fields
+    fn tuple(&mut self, id: &str, fields: &[&Field]) -> String {
+        let types = fields
+            .iter()
+            .map(|f| self.field_type(id, f))
+            .collect::<Vec<String>>();
+        match types.as_slice() {
+            [] => "null".to_string(),
+            [newtype] => newtype.clone(),
+            _ => format!("[{}]", types.join(", ")),
+        }
+    }
+
+    /// `{ key: T; ... }` of named fields, intersected with the flattened types
+    fn object(&mut self, id: &str, entry: &Entry, fields: &[&Field], indent: &str) -> String {
+        let mut lines = Vec::<String>::new();
+        let mut flattened = Vec::<String>::new();
+        for f in fields {
+            let ty = self.field_type(id, f);
+            if f.serde.flatten {
+                flattened.push(ty);
+                continue;
+            }
+            let json_key = match f.json_key(&entry.serde, true) {
+                Some(json_key) => json_key,
+                None => continue,
+            };
+            // `Option` fields and the fields that can be left out are optional properties.
+            // `None` is written as `null` unless it is skipped.
+            let (optional, ty) = match (ty.strip_suffix(" | null"), &f.serde.skip_serializing_if) {
+                (Some(inner), Some(_)) => (true, inner.to_string()),
+                (Some(_), None) => (true, ty),
+                (None, skip) => (skip.is_some(), ty),
+            };
+            let mut line = jsdoc(f.doc.as_deref(), &format!("{}  ", indent));
+            line.push_str(
+                format!(
+                    "{}  {}{}: {};",
+                    indent,
+                    key(&json_key),
+                    if optional { "?" } else { "" },
+                    ty
+                )
+                .as_str(),
+            );
+            lines.push(line);
+        }
+        let object = if lines.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{\n{}\n{}}}", lines.join("\n"), indent)
+        };
+        if flattened.is_empty() {
+            object
+        } else {
+            [vec![object], flattened].concat().join(" & ")
+        }
+    }
+
+    /// Discriminated union of the variants, following the enum tagging
+    fn union(&mut self, id: &str, entry: &Entry) -> String {
+        let tagging = entry.serde.tagging();
+        let mut variants = Vec::<String>::new();
+        for variant in entry.get_variants() {
+            let name = match variant.json_name(&entry.serde, true) {
+                Some(name) => format!("{:?}", name),
+                None => continue,
+            };
+            let payload = self.payload(id, entry, &variant);
+            let ty = match (&tagging, payload) {
+                (Tagging::External, None) => name,
+                (Tagging::External, Some(payload)) => format!("{{ {}: {} }}", name, payload),
+                (Tagging::Internal(tag), None) | (Tagging::Adjacent(tag, _), None) => {
+                    format!("{{ {}: {} }}", key(tag), name)
+                }
+                (Tagging::Internal(tag), Some(payload)) => {
+                    format!("{{ {}: {} }} & {}", key(tag), name, payload)
+                }
+                (Tagging::Adjacent(tag, content), Some(payload)) => format!(
+                    "{{ {}: {}; {}: {} }}",
+                    key(tag),
+                    name,
+                    key(content),
+                    payload
+                ),
+                (Tagging::Untagged, None) => "null".to_string(),
+                (Tagging::Untagged, Some(payload)) => payload,
+            };
+            variants.push(format!("{}  | {}", jsdoc(variant.doc.as_deref(), "  "), ty));
+        }
+        if variants.is_empty() {
+            "  never".to_string()
+        } else {
+            variants.join("\n")
+        }
+    }
+
+    /// Type of the variant content, `None` for unit variants
+    fn payload(&mut self, id: &str, entry: &Entry, variant: &Variant) -> Option<String> {
+        let fields = entry
+            .members
+            .iter()
+            .filter(|f| f.variant() == Some(variant.name.as_str()) && !f.serde.skip_serializing)
+            .collect::<Vec<&Field>>();
+        match variant.kind {
+            VariantKind::Unit => None,
+            VariantKind::Tuple => Some(self.tuple(id, &fields)),
+            VariantKind::Struct => Some(self.object(id, entry, &fields, "  ")),
+        }
+    }
+
+    /// Type of a field. Integers with a custom serializer are strings.
+    fn field_type(&mut self, id: &str, f: &Field) -> String {
+        if let Some(with) = &f.serde.with.ser {
+            return if is_integer(&f.ty) {
+                if f.ty.starts_with("Option<") {
+                    format!("string | null /* {} */", with)
+                } else {
+                    format!("string /* {} */", with)
+                }
+            } else {
+                format!("unknown /* {} */", with)
+            };
+        }
+        match syn::parse_str::<Type>(&f.ty) {
+            Ok(ty) => {
+                let resolve = schema::field_resolver(self.collection, id, f);
+                self.type_name(&ty, &resolve)
+            }
+            Err(_) => "unknown".to_string(),
+        }
+    }
+
+    /// TypeScript type of a Rust type. `resolve` finds the entry of a type name.
+    fn type_name(&mut self, ty: &Type, resolve: &dyn Fn(&str) -> Option<String>) -> String {
+        match shape(ty) {
+            Shape::Optional(t) => format!("{} | null", self.type_name(t, resolve)),
+            Shape::Array { item, .. } => array(&self.type_name(item, resolve)),
+            Shape::Map(value) => format!("Record<string, {}>", self.type_name(value, resolve)),
+            Shape::Tuple(elems) if elems.is_empty() => "null".to_string(),
+            Shape::Tuple(elems) => format!(
+                "[{}]",
+                elems
+                    .iter()
+                    .map(|e| self.type_name(e, resolve))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Shape::Named(name) => {
+                if let Some(id) = resolve(&name) {
+                    self.refs.insert(id.clone());
+                    self.names[&id].clone()
+                } else if let Some(json) = schema::json_type(&name) {
+                    ts_type(json).to_string()
+                } else {
+                    format!("unknown /* {} */", name)
+                }
+            }
+            Shape::Unknown => "unknown".to_string(),
+        }
+    }
+}
+
+/// `T[]`, with parentheses around unions
+fn array(item: &str) -> String {
+    if item.contains(' ') {
+        format!("({})[]", item)
+    } else {
+        format!("{}[]", item)
+    }
+}
@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Split the path into the nearest existing directory and the remaining (relative) part.
+/// The source folder does not have to exist in the working tree, only in the revision.
+fn split_existing(rust_path: &str) -> (PathBuf, PathBuf) {
+    let mut base = PathBuf::from(rust_path);
+    let mut rest = Vec::new();
+    while !base.is_dir() {
+        match base.file_name() {
+            Some(name) => rest.insert(0, name.to_os_string()),
+            None => panic!("no directory found for path: {}", rust_path),
+        }
+        if !base.pop() {
+            base = PathBuf::from(".");
+            break;
+        }
+    }
+    if base.as_os_str().is_empty() {
+        base = PathBuf::from(".");
+    }
+    (base, rest.iter().collect())
+}
+
+/// Run a git command in `dir` and return its standard output.
+fn git(dir: &Path, args: &[&str]) -> Vec<u8> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .expect("Unable to run git");
+    if !output.status.success() {
+        panic!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    output.stdout
+}
+
+/// Find *.rs recursively in a git revision. The returned paths are relative to `rust_path`.
+pub fn find_rust_files(rust_path: &str, rev: &str) -> Vec<PathBuf> {
+    let (base, rest) = split_existing(rust_path);
+    let target = if rest.as_os_str().is_empty() {
+        ".".to_string()
+    } else {
+        rest.to_str().unwrap().to_string()
+    };
+    let tree = format!("{}^{{tree}}", rev);
+    let stdout = git(
+        &base,
+        &["ls-tree", "-r", "-z", "--name-only", &tree, "--", &target],
+    );
+    String::from_utf8(stdout)
+        .expect("git returned an invalid path")
+        .split('\0')
+        .filter(|p| p.ends_with(".rs"))
+        .map(|p| {
+            let path = Path::new(p);
+            if rust_path.ends_with(".rs") {
+                PathBuf::from(path.file_name().unwrap())
+            } else {
+                path.strip_prefix(&rest).unwrap_or(path).to_path_buf()
+            }
+        })
+        .collect()
+}
+
+/// Parse a rust file of a git revision into a TokenTree. `path` is relative to `rust_path`.
+pub fn parse_file(rust_path: &str, rev: &str, path: &Path) -> syn::File {
+    let (base, rest) = split_existing(rust_path);
+    let full = if rust_path.ends_with(".rs") {
+        rest
+    } else {
+        rest.join(path)
+    };
+    let object = format!("{}:./{}", rev, full.to_str().unwrap());
+    let src = String::from_utf8(git(&base, &["show", &object])).expect("Unable to read file");
+    syn::parse_file(&src).expect("Unable to parse file")
+}
+
+/// Commit id of a git revision
+pub fn commit(rust_path: &str, rev: &str) -> String {
+    let (base, _) = split_existing(rust_path);
+    let commit = format!("{}^{{commit}}", rev);
+    String::from_utf8(git(&base, &["rev-parse", "--verify", &commit]))
+        .expect("git returned an invalid commit id")
+        .trim()
+        .to_string()
+}
+
+/// Folder of the source files relative to the root of their git repository (`tendermint/src/`),
+/// `None` if the path is not in a git repository.
+pub fn repo_path(rust_path: &str) -> Option<String> {
+    let (base, rest) = split_existing(rust_path);
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&base)
+        .args(["rev-parse", "--show-prefix"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+    let prefix = String::from_utf8(output.stdout).ok()?;
+    let mut path = PathBuf::from(prefix.trim());
+    path.push(if rust_path.ends_with(".rs") {
+        rest.parent().unwrap_or_else(|| Path::new(""))
+    } else {
+        &rest
+    });
+    let path = path.to_str()?.to_string();
+    Some(if path.is_empty() || path.ends_with('/') {
+        path
+    } else {
+        format!("{}/", path)
+    })
+}
@@ -1,17 +1,30 @@
 mod core;
 mod fs;
+mod git;
 
+use core::coverage::{TestCoverage, TestFile};
+use core::docs::DocCoverage;
+use core::fixtures::{FixtureMap, FixtureReport};
+use core::graph::{Direction, Graph};
+use core::group::{GroupBy, Grouping};
+use core::jsonpath::Walker;
+use core::pydantic::Models;
+use core::roundtrip::RoundTrip;
+use core::sample::Sampler;
+use core::schema::Generator;
+use core::stats::{Sort, Stats};
+use core::typescript::Declarations;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
 
+// draw.io output options
 #[derive(Debug, StructOpt)]
-struct Cli {
-    /// Input folder for the Tendermint source code.
-    #[structopt(name = "source_directory", parse(from_os_str))]
-    rust_path: PathBuf,
-
+struct Render {
     /// Use this flag to omit printing the CSV header.
     /// This is useful when you want to concatenate multiple outputs.
     #[structopt(short, long)]
@@ -24,36 +37,1067 @@ struct Cli {
     /// Output file
     #[structopt(short, long, parse(from_os_str))]
     output: Option<PathBuf>,
-}
 
-fn main() {
-    let args = Cli::from_args();
-    let rust_path: &str = args.rust_path.to_str().unwrap();
-    let no_header: bool = args.no_header;
-    let only_json: bool = args.json;
-    let output: Option<PathBuf> = args.output;
+    /// draw.io template file (TOML) with the title, namespace, layout, spacing, palette and label.
+    #[structopt(short, long, parse(from_os_str))]
+    template: Option<PathBuf>,
+
+    /// Diagram title. Overrides the template.
+    #[structopt(long)]
+    title: Option<String>,
+
+    /// Prefix of the draw.io node ids. Overrides the template.
+    #[structopt(long)]
+    namespace: Option<String>,
 
-    let files = fs::find_rust_files(&rust_path);
-    let mut collection = core::db::Collection::new();
-    for file in files {
-        let syntax = fs::parse_file(file.clone());
+    /// List the fields with their types, JSON keys and serde markers in the nodes.
+    #[structopt(long)]
+    class_labels: bool,
 
-        let id_prefix = if rust_path.ends_with(".rs") {
-            file.file_name().unwrap().to_str()
-        } else {
-            file.strip_prefix(&rust_path).unwrap().to_str()
+    /// Link of the nodes to their definition, for example
+    /// https://github.com/org/repo/blob/{rev}/{path}#L{line}. Overrides the template.
+    #[structopt(long)]
+    source_url: Option<String>,
+}
+
+impl Render {
+    /// Load the template and apply the command-line overrides.
+    fn template(&self) -> core::template::Template {
+        let mut template = match &self.template {
+            Some(t) => core::template::Template::from_file(t),
+            None => core::template::Template::default(),
+        };
+        if let Some(title) = &self.title {
+            template.title = title.clone();
+        }
+        if let Some(namespace) = &self.namespace {
+            template.namespace = namespace.clone();
         }
-        .unwrap()
-        .strip_suffix(".rs")
-        .unwrap();
+        template.class_labels |= self.class_labels;
+        if let Some(source_url) = &self.source_url {
+            template.source_url = source_url.clone();
+        }
+        template
+    }
 
-        collection.add_items(syntax.items, id_prefix);
+    /// Write the result into the output file or to the screen.
+    fn write(&self, result: &str) {
+        write(&self.output, result);
     }
+}
+
+/// Write the result into the output file or to the screen.
+fn write(output: &Option<PathBuf>, result: &str) {
     if let Some(o) = output {
         let mut f = File::create(o).expect("file creation failed");
-        f.write_all(collection.parse_to_csv(only_json, no_header).as_bytes())
-            .expect("file write failed");
+        f.write_all(result.as_bytes()).expect("file write failed");
+    } else {
+        println!("{}", result);
+    }
+}
+
+// Where to read the analysis from
+#[derive(Debug, StructOpt)]
+struct Input {
+    /// Source folder or snapshot file.
+    #[structopt(short, long, default_value = ".")]
+    path: String,
+
+    /// Read the source code from this git revision of the local repository.
+    #[structopt(short, long)]
+    rev: Option<String>,
+}
+
+impl Input {
+    fn open(&self) -> core::db::Collection {
+        open(&self.path, self.rev.as_deref())
+    }
+}
+
+/// Output format of the queries
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Text,
+    Json,
+    /// draw.io CSV diagram
+    Csv,
+    /// TypeScript declarations of the JSON
+    Typescript,
+    /// pydantic v2 models of the JSON
+    Pydantic,
+}
+
+/// Entry id of a type name given on the command line, exit with an error if it is unknown or ambiguous
+fn find(graph: &Graph, name: &str) -> String {
+    graph
+        .find(name)
+        .unwrap_or_else(|e| Error::with_description(&e, ErrorKind::InvalidValue).exit())
+}
+
+/// Exit with an error for the formats that a command doesn't support
+fn unsupported(format: Format) -> ! {
+    Error::with_description(
+        &format!("--format {:?} is not supported by this command", format).to_lowercase(),
+        ErrorKind::InvalidValue,
+    )
+    .exit()
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "typescript" => Ok(Format::Typescript),
+            "pydantic" => Ok(Format::Pydantic),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+// Neighborhood of selected types
+#[derive(Debug, StructOpt)]
+struct Focus {
+    /// Render only these types (comma-separated) and their neighborhood.
+    /// The neighbors outside of the radius are collapsed into "+k more" nodes.
+    #[structopt(long, use_delimiter = true)]
+    focus: Vec<String>,
+
+    /// Number of hops around the focused types.
+    #[structopt(long, default_value = "1")]
+    radius: usize,
+
+    /// Neighbors to show: downstream (field types), upstream (embedding types) or both.
+    #[structopt(long, default_value = "both")]
+    direction: Direction,
+}
+
+impl Focus {
+    /// The focused view of the collection, or the whole collection without `--focus`.
+    fn apply(&self, collection: core::db::Collection) -> core::db::Collection {
+        if self.focus.is_empty() {
+            return collection;
+        }
+        let graph = Graph::new(&collection);
+        let roots = self
+            .focus
+            .iter()
+            .map(|name| find(&graph, name))
+            .collect::<Vec<String>>();
+        graph.focus(&roots, self.radius, self.direction)
+    }
+}
+
+// Diagram simplification
+#[derive(Debug, StructOpt)]
+struct Simplify {
+    /// Remove the edges that are implied by a longer path (transitive reduction).
+    #[structopt(long)]
+    reduce: bool,
+
+    /// Hide the edges to leaf types (types without dependencies, like Height or Hash) that are embedded by at
+    /// least this many types.
+    #[structopt(long)]
+    hide_leaves: Option<usize>,
+
+    /// Merge the edges between the same two types into one, labeled with the number of fields.
+    #[structopt(long)]
+    merge_edges: bool,
+}
+
+impl Simplify {
+    fn apply(&self, mut collection: core::db::Collection) -> core::db::Collection {
+        if self.merge_edges {
+            core::simplify::merge_parallel(&mut collection);
+        }
+        if let Some(min_fan_in) = self.hide_leaves {
+            core::simplify::hide_leaves(&mut collection, min_fan_in);
+        }
+        if self.reduce {
+            core::simplify::reduce(&mut collection);
+        }
+        collection
+    }
+}
+
+/// Write a collection (or a part of it) as a list of ids, a JSON graph or a draw.io diagram.
+fn write_view(format: Format, render: &Render, view: &core::db::Collection) {
+    match format {
+        Format::Text => {
+            let mut lines = Vec::<String>::new();
+            for (id, entry) in view.entries() {
+                lines.push(match entry.location() {
+                    Some(location) => format!("{} ({})", id, location),
+                    None => id.clone(),
+                });
+                for stub in view.stubs().iter().filter(|s| &s.id == id) {
+                    lines.push(format!("  {}", stub.name()));
+                }
+            }
+            render.write(&lines.join("\n"));
+        }
+        Format::Json => {
+            let source_url = render.template().source_url;
+            render.write(
+                &serde_json::to_string_pretty(&serde_json::json!({
+                    "entries": view.entries().map(|(id, _)| id).collect::<Vec<&String>>(),
+                    "edges": view.edges(),
+                    "stubs": view.stubs(),
+                    "locations": locations(view, &source_url),
+                }))
+                .unwrap(),
+            )
+        }
+        Format::Csv => {
+            render.write(&view.parse_to_csv(&render.template(), render.json, render.no_header))
+        }
+        Format::Typescript => render.write(&Declarations::new(view).render()),
+        Format::Pydantic => render.write(&Models::new(view).render()),
+    }
+}
+
+/// Definitions of the entries with their links, by id
+fn locations(
+    collection: &core::db::Collection,
+    source_url: &str,
+) -> BTreeMap<String, serde_json::Value> {
+    collection
+        .entries()
+        .filter_map(|(id, entry)| {
+            let location = entry.location()?;
+            Some((
+                id.clone(),
+                serde_json::json!({
+                    "file": location.file,
+                    "line": location.line,
+                    "column": location.column,
+                    "url": collection.source_url(id, source_url),
+                }),
+            ))
+        })
+        .collect()
+}
+
+// Common options of the graph queries
+#[derive(Debug, StructOpt)]
+struct Query {
+    #[structopt(flatten)]
+    input: Input,
+
+    /// Output format: text, json, csv (draw.io diagram of the result), typescript (declarations of the result)
+    /// or pydantic (models of the result).
+    #[structopt(short, long, default_value = "text")]
+    format: Format,
+
+    #[structopt(flatten)]
+    render: Render,
+}
+
+impl Query {
+    /// Write the result of a query that selects entries: `found` are the ids with their distance from `start`.
+    fn write_selection(
+        &self,
+        collection: &core::db::Collection,
+        start: &str,
+        found: &[(String, usize)],
+    ) {
+        match self.format {
+            Format::Text => self.render.write(
+                &found
+                    .iter()
+                    .map(
+                        |(id, depth)| match collection.get(id).and_then(|e| e.location()) {
+                            Some(location) => format!("{} {} ({})", depth, id, location),
+                            None => format!("{} {}", depth, id),
+                        },
+                    )
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            ),
+            Format::Json => {
+                let locations = locations(collection, &self.render.template().source_url);
+                self.render.write(
+                    &serde_json::to_string_pretty(
+                        &found
+                            .iter()
+                            .map(|(id, depth)| {
+                                serde_json::json!({"id": id, "depth": depth, "location": locations.get(id)})
+                            })
+                            .collect::<Vec<serde_json::Value>>(),
+                    )
+                    .unwrap(),
+                )
+            }
+            Format::Csv | Format::Typescript | Format::Pydantic => {
+                let mut ids = found
+                    .iter()
+                    .map(|(id, _)| id.clone())
+                    .collect::<BTreeSet<String>>();
+                ids.insert(start.to_string());
+                self.write_subset(collection, &ids);
+            }
+        }
+    }
+
+    /// Write a diagram (or the declarations, or the models) of the selected entries
+    fn write_subset(&self, collection: &core::db::Collection, ids: &BTreeSet<String>) {
+        write_view(self.format, &self.render, &collection.subset(ids));
+    }
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Compare two analyses: added, removed and renamed types, changed fields, colors and edges.
+    Diff {
+        /// Old side: a source folder, a snapshot file or a git revision of --path.
+        old: String,
+
+        /// New side: a source folder, a snapshot file or a git revision of --path.
+        new: String,
+
+        /// Source folder used for the git revisions.
+        #[structopt(short, long, default_value = ".")]
+        path: String,
+
+        /// Render a draw.io CSV diagram with the additions in green and the removals in red
+        /// instead of the text report.
+        #[structopt(short, long)]
+        diagram: bool,
+
+        #[structopt(flatten)]
+        render: Render,
+    },
+
+    /// Save the analysis into a versioned snapshot file (JSON). Snapshots can be used instead of the source folder
+    /// for rendering, diffing and querying without parsing the source code again.
+    Snapshot {
+        /// Input folder for the source code.
+        #[structopt(name = "source_directory")]
+        rust_path: String,
+
+        /// Read the source code from this git revision of the local repository.
+        #[structopt(short, long)]
+        rev: Option<String>,
+
+        /// Crate name used to qualify the ids when merging. Default: the folder name above `src`.
+        #[structopt(short, long)]
+        crate_name: Option<String>,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Merge snapshots of several crates or repositories into one snapshot with crate-qualified ids.
+    Merge {
+        /// Snapshot files
+        #[structopt(required = true, min_values = 2, parse(from_os_str))]
+        snapshots: Vec<PathBuf>,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Transitive dependencies of a type: the types of its fields, their fields' types, and so on.
+    Deps {
+        /// Type name, for example block::Height
+        type_name: String,
+
+        /// Maximum distance from the type
+        #[structopt(short, long)]
+        depth: Option<usize>,
+
+        #[structopt(flatten)]
+        query: Query,
+    },
+
+    /// Transitive dependents of a type: everything that embeds it.
+    Rdeps {
+        /// Type name, for example block::Height
+        type_name: String,
+
+        /// Maximum distance from the type
+        #[structopt(short, long)]
+        depth: Option<usize>,
+
+        #[structopt(flatten)]
+        query: Query,
+    },
+
+    /// Shortest chain of fields from one type to another.
+    Path {
+        /// The embedding type
+        from: String,
+
+        /// The embedded type
+        to: String,
+
+        #[structopt(flatten)]
+        query: Query,
+    },
+
+    /// The subgraph reachable from a root type.
+    Closure {
+        /// Root type name
+        #[structopt(long)]
+        root: String,
+
+        #[structopt(flatten)]
+        query: Query,
+    },
+
+    /// Recursive types: the strongly connected components of the graph with the fields that form them.
+    Cycles {
+        #[structopt(flatten)]
+        query: Query,
+    },
+
+    /// Metrics of every public type: fan-in, fan-out, nesting depth, number of fields and betweenness centrality,
+    /// with totals per color and kind.
+    Stats {
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Count only JSON serializable types.
+        #[structopt(short, long)]
+        json: bool,
+
+        /// Output format: text (table), json or csv (table for spreadsheets).
+        #[structopt(short, long, default_value = "text")]
+        format: Format,
+
+        /// Sort by id, fan-in, fan-out, depth, fields or betweenness.
+        #[structopt(short, long, default_value = "id")]
+        sort: Sort,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// JSON Schema (draft 2020-12) of the serializable types, from the serde attributes.
+    Schema {
+        /// Type name, for example block::Block. Default: every serializable type.
+        type_name: Option<String>,
+
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Describe the JSON accepted by Deserialize instead of the JSON written by Serialize.
+        #[structopt(long)]
+        deserialize: bool,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Sample JSON value of a serializable type, as written by Serialize.
+    Sample {
+        /// Type name, for example block::Block
+        type_name: String,
+
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Check JSON fixture files against the JSON accepted by Deserialize: unknown keys, missing required fields and
+    /// type mismatches.
+    CheckFixtures {
+        /// TOML file with the [[fixture]] tables: path (file or folder), type and pointer
+        #[structopt(long, parse(from_os_str))]
+        map: PathBuf,
+
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Follow a JSON path (like result.block.header.last_block_id) through the serialized types: the Rust type, field,
+    /// source location and color of every hop, and where a custom serializer takes over.
+    Jsonpath {
+        /// Type of the JSON document, for example block::Block
+        root: String,
+
+        /// JSON path in the document, for example header.last_block_id.parts.hash
+        json_path: String,
+
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Output format: text or json.
+        #[structopt(short, long, default_value = "text")]
+        format: Format,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Rust test module with a round-trip serialization test of every serializable public type.
+    RoundTrip {
+        /// Crate name in the paths of the types. Default: the analyzed crate.
+        #[structopt(long)]
+        crate_name: Option<String>,
+
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Output file, for example tests/round_trip.rs
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// Coverage of the JSON serializable types by the tests: the serde (`serde_json::from_str::<T>`, `to_string(&t)`)
+    /// and protobuf (`T::decode_vec`, `t.encode_vec()`) calls of the `#[cfg(test)]` modules and the `tests/` folder.
+    TestCoverage {
+        #[structopt(flatten)]
+        input: Input,
+
+        /// Output format: text (the untested types), json or csv (every type with its number of calls).
+        #[structopt(short, long, default_value = "text")]
+        format: Format,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+
+    /// HTML documentation page of the public types from the `///` comments, or the doc coverage per module.
+    Docs {
+        #[structopt(flatten)]
+        query: Query,
+
+        /// Print the doc coverage per module (in --format) instead of the HTML page.
+        #[structopt(long)]
+        coverage: bool,
+    },
+
+    /// Check the wire (JSON) compatibility of the serializable types between two versions.
+    /// Every change is classified as breaking or non-breaking for serialization and deserialization.
+    Compat {
+        /// Old side: a source folder, a snapshot file or a git revision of --path.
+        old: String,
+
+        /// New side: a source folder, a snapshot file or a git revision of --path.
+        new: String,
+
+        /// Source folder used for the git revisions.
+        #[structopt(short, long, default_value = ".")]
+        path: String,
+
+        /// Output file
+        #[structopt(short, long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct Cli {
+    /// Input folder for the Tendermint source code or a snapshot file. Required without a subcommand.
+    #[structopt(name = "source_directory", parse(from_os_str))]
+    rust_path: Option<PathBuf>,
+
+    /// Read the source code from this git revision (commit, branch or tag) of the local repository
+    /// instead of the working tree.
+    #[structopt(short, long)]
+    rev: Option<String>,
+
+    #[structopt(flatten)]
+    render: Render,
+
+    #[structopt(flatten)]
+    focus: Focus,
+
+    #[structopt(flatten)]
+    simplify: Simplify,
+
+    /// Label the links with the field names and multiplicities (1, 0..1, 0..*, keyed).
+    #[structopt(long)]
+    edge_labels: bool,
+
+    /// Draw the enum variants with a payload as child nodes of the enum.
+    #[structopt(long)]
+    expand_variants: bool,
+
+    /// Output format: csv (draw.io diagram), text, json, typescript (declarations of the JSON)
+    /// or pydantic (models of the JSON).
+    #[structopt(short, long, default_value = "csv")]
+    format: Format,
+
+    /// Aggregate the types: module (one node per module).
+    #[structopt(long)]
+    group_by: Option<GroupBy>,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+fn main() {
+    let args = Cli::from_args();
+    match &args.command {
+        None => {
+            let rust_path: &str = match &args.rust_path {
+                Some(p) => p.to_str().unwrap(),
+                None => Error::with_description(
+                    "The following required argument was not provided: <source_directory>",
+                    ErrorKind::MissingRequiredArgument,
+                )
+                .exit(),
+            };
+            let mut collection = args.focus.apply(open(rust_path, args.rev.as_deref()));
+            if args.expand_variants {
+                collection.expand_variants();
+            }
+            let mut collection = args.simplify.apply(collection);
+            if args.edge_labels {
+                // One label node for the links between the same two types
+                core::simplify::merge_parallel(&mut collection);
+                collection.label_edges();
+            }
+            match args.group_by {
+                Some(GroupBy::Module) => {
+                    let grouping = Grouping::new(&collection, args.render.json);
+                    args.render.write(&match args.format {
+                        Format::Text => grouping.to_text(),
+                        Format::Json => serde_json::to_string_pretty(&grouping).unwrap(),
+                        Format::Csv => {
+                            grouping.to_csv(&args.render.template(), args.render.no_header)
+                        }
+                        format => unsupported(format),
+                    });
+                }
+                None => write_view(args.format, &args.render, &collection),
+            }
+        }
+        Some(Command::Diff {
+            old,
+            new,
+            path,
+            diagram,
+            render,
+        }) => {
+            let old = load(old, path);
+            let new = load(new, path);
+            let diff = core::diff::Diff::new(&old, &new);
+            if *diagram {
+                render.write(&diff.to_csv(
+                    &old,
+                    &new,
+                    &render.template(),
+                    render.json,
+                    render.no_header,
+                ));
+            } else {
+                render.write(&diff.to_text());
+            }
+        }
+        Some(Command::Snapshot {
+            rust_path,
+            rev,
+            crate_name,
+            output,
+        }) => {
+            let mut collection = analyze(rust_path, rev.as_deref());
+            // The links of a snapshot must not depend on the folder it is rendered from
+            if let Ok(path) = std::fs::canonicalize(rust_path) {
+                collection.sources[0].path = path.to_str().unwrap().to_string();
+            }
+            if let Some(crate_name) = crate_name {
+                collection.sources[0].crate_name = crate_name.clone();
+            }
+            write(output, &core::snapshot::save(collection));
+        }
+        Some(Command::Merge { snapshots, output }) => {
+            let merged = snapshots
+                .iter()
+                .map(|s| core::snapshot::load(s))
+                .collect::<Result<Vec<core::db::Collection>, String>>()
+                .and_then(core::snapshot::merge)
+                .unwrap_or_else(|e| Error::with_description(&e, ErrorKind::InvalidValue).exit());
+            write(output, &core::snapshot::save(merged));
+        }
+        Some(Command::Deps {
+            type_name,
+            depth,
+            query,
+        })
+        | Some(Command::Rdeps {
+            type_name,
+            depth,
+            query,
+        }) => {
+            let direction = match &args.command {
+                Some(Command::Deps { .. }) => Direction::Downstream,
+                _ => Direction::Upstream,
+            };
+            let collection = query.input.open();
+            let graph = Graph::new(&collection);
+            let id = find(&graph, type_name);
+            let found = graph.reach(&id, direction, *depth);
+            query.write_selection(&collection, &id, &found);
+        }
+        Some(Command::Path { from, to, query }) => {
+            let collection = query.input.open();
+            let graph = Graph::new(&collection);
+            let (from, to) = (find(&graph, from), find(&graph, to));
+            let path = graph.path(&from, &to).unwrap_or_else(|| {
+                Error::with_description(
+                    &format!("no path from {} to {}", from, to),
+                    ErrorKind::InvalidValue,
+                )
+                .exit()
+            });
+            match query.format {
+                Format::Text => {
+                    let mut lines = vec![from.clone()];
+                    for e in &path {
+                        lines.push(format!("  .{} -> {}", e.fields.join(", ."), e.to));
+                    }
+                    query.render.write(&lines.join("\n"));
+                }
+                Format::Json => query
+                    .render
+                    .write(&serde_json::to_string_pretty(&path).unwrap()),
+                Format::Csv | Format::Typescript | Format::Pydantic => {
+                    let ids = path
+                        .iter()
+                        .map(|e| e.to.clone())
+                        .chain(std::iter::once(from))
+                        .collect();
+                    query.write_subset(&collection, &ids);
+                }
+            }
+        }
+        Some(Command::Closure { root, query }) => {
+            let collection = query.input.open();
+            let graph = Graph::new(&collection);
+            let root = find(&graph, root);
+            write_view(
+                query.format,
+                &query.render,
+                &collection.subset(&graph.closure(&root)),
+            );
+        }
+        Some(Command::Cycles { query }) => {
+            let collection = query.input.open();
+            let cycles = Graph::new(&collection).cycles();
+            match query.format {
+                Format::Text => {
+                    let mut lines = Vec::<String>::new();
+                    for cycle in &cycles {
+                        lines.push(cycle.ids.join(", "));
+                        for e in &cycle.edges {
+                            for field in &e.fields {
+                                lines.push(format!(
+                                    "  {}.{} -> {}{}",
+                                    e.from,
+                                    field,
+                                    e.to,
+                                    if e.inline.contains(field) {
+                                        " (inline)"
+                                    } else {
+                                        ""
+                                    }
+                                ));
+                            }
+                        }
+                        if cycle.unboxed {
+                            lines.push(
+                                "  warning: recursion without indirection (Box, Vec, ...)"
+                                    .to_string(),
+                            );
+                        }
+                    }
+                    query.render.write(&lines.join("\n"));
+                }
+                Format::Json => query
+                    .render
+                    .write(&serde_json::to_string_pretty(&cycles).unwrap()),
+                Format::Csv | Format::Typescript | Format::Pydantic => query.write_subset(
+                    &collection,
+                    &cycles.into_iter().flat_map(|c| c.ids).collect(),
+                ),
+            }
+        }
+        Some(Command::Stats {
+            input,
+            json,
+            format,
+            sort,
+            output,
+        }) => {
+            let mut stats = Stats::new(&input.open(), *json);
+            stats.sort(*sort);
+            write(
+                output,
+                &match format {
+                    Format::Text => stats.to_text(),
+                    Format::Json => serde_json::to_string_pretty(&stats).unwrap(),
+                    Format::Csv => stats.to_csv(),
+                    format => unsupported(*format),
+                },
+            );
+        }
+        Some(Command::Schema {
+            type_name,
+            input,
+            deserialize,
+            output,
+        }) => {
+            let collection = input.open();
+            let roots = match type_name {
+                Some(name) => vec![find(&Graph::new(&collection), name)],
+                None => Generator::roots(&collection, !*deserialize),
+            };
+            let document = Generator::new(&collection, !*deserialize).document(&roots);
+            write(output, &serde_json::to_string_pretty(&document).unwrap());
+        }
+        Some(Command::Sample {
+            type_name,
+            input,
+            output,
+        }) => {
+            let collection = input.open();
+            let id = find(&Graph::new(&collection), type_name);
+            let sample = Sampler::new(&collection).sample(&id);
+            write(output, &serde_json::to_string_pretty(&sample).unwrap());
+        }
+        Some(Command::CheckFixtures { map, input, output }) => {
+            let collection = input.open();
+            let report = FixtureReport::new(&collection, &FixtureMap::from_file(map))
+                .unwrap_or_else(|e| Error::with_description(&e, ErrorKind::InvalidValue).exit());
+            write(output, &report.to_text());
+            if report.failed() {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Jsonpath {
+            root,
+            json_path,
+            input,
+            format,
+            output,
+        }) => {
+            let collection = input.open();
+            let root = find(&Graph::new(&collection), root);
+            let (hops, followed) =
+                Walker::new(&collection).walk(&root, &core::jsonpath::parse(json_path));
+            write(
+                output,
+                &match format {
+                    Format::Text => core::jsonpath::to_text(&hops),
+                    Format::Json => serde_json::to_string_pretty(&hops).unwrap(),
+                    format => unsupported(*format),
+                },
+            );
+            if !followed {
+                let reason = hops.last().and_then(|h| h.note.clone()).unwrap_or_default();
+                Error::with_description(
+                    &format!("{} can't be followed: {}", json_path, reason),
+                    ErrorKind::InvalidValue,
+                )
+                .exit();
+            }
+        }
+        Some(Command::RoundTrip {
+            crate_name,
+            input,
+            output,
+        }) => {
+            let collection = input.open();
+            let crate_name = crate_name
+                .clone()
+                .unwrap_or_else(|| collection.sources[0].crate_name.clone());
+            write(output, &RoundTrip::new(&collection, &crate_name).render());
+        }
+        Some(Command::TestCoverage {
+            input,
+            format,
+            output,
+        }) => {
+            if input.path.ends_with(".json") && input.rev.is_none() {
+                Error::with_description(
+                    "test coverage needs the source folder, not a snapshot",
+                    ErrorKind::InvalidValue,
+                )
+                .exit()
+            }
+            let collection = input.open();
+            let files = test_files(&input.path, input.rev.as_deref());
+            let coverage =
+                TestCoverage::new(&collection, &collection.sources[0].crate_name, &files);
+            write(
+                output,
+                &match format {
+                    Format::Text => coverage.to_text(),
+                    Format::Json => serde_json::to_string_pretty(&coverage).unwrap(),
+                    Format::Csv => coverage.to_csv(),
+                    format => unsupported(*format),
+                },
+            );
+        }
+        Some(Command::Docs { query, coverage }) => {
+            let collection = query.input.open();
+            let render = &query.render;
+            if *coverage {
+                let coverage = DocCoverage::new(&collection, render.json);
+                render.write(&match query.format {
+                    Format::Text => coverage.to_text(),
+                    Format::Json => serde_json::to_string_pretty(&coverage).unwrap(),
+                    Format::Csv => coverage.to_csv(),
+                    format => unsupported(format),
+                });
+            } else {
+                render.write(&core::docs::to_html(
+                    &collection,
+                    &render.template(),
+                    render.json,
+                ));
+            }
+        }
+        Some(Command::Compat {
+            old,
+            new,
+            path,
+            output,
+        }) => {
+            let report = core::compat::Report::new(&load(old, path), &load(new, path));
+            write(output, &report.to_text());
+            if report.breaking() {
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Load one side of a comparison: a source folder, file or snapshot if it exists, a git revision of `path` otherwise.
+fn load(side: &str, path: &str) -> core::db::Collection {
+    if Path::new(side).exists() {
+        open(side, None)
+    } else {
+        analyze(path, Some(side))
+    }
+}
+
+/// Load a snapshot file or analyze the source code.
+fn open(rust_path: &str, rev: Option<&str>) -> core::db::Collection {
+    if rust_path.ends_with(".json") && rev.is_none() {
+        core::snapshot::load(Path::new(rust_path))
+            .unwrap_or_else(|e| Error::with_description(&e, ErrorKind::InvalidValue).exit())
+    } else {
+        analyze(rust_path, rev)
+    }
+}
+
+/// Crate name of a source folder: the folder name above `src`, or the folder (file) name.
+/// Relative paths are resolved first, so `src` is named after the folder above it.
+fn crate_name(rust_path: &str) -> String {
+    let path = std::fs::canonicalize(rust_path).unwrap_or_else(|_| PathBuf::from(rust_path));
+    let mut names = path
+        .components()
+        .rev()
+        .filter_map(|c| c.as_os_str().to_str())
+        .filter(|c| *c != "." && *c != "/");
+    let mut name = names.next().unwrap_or("crate");
+    if name.ends_with(".rs") {
+        name = names.next().unwrap_or("crate");
+    }
+    if name == "src" {
+        name = names.next().unwrap_or("crate");
+    }
+    name.to_string()
+}
+
+/// Parse the source code in the folder (or file) into a collection and resolve the dependencies.
+/// If a git revision is given, the files are read from the local git object database.
+fn analyze(rust_path: &str, rev: Option<&str>) -> core::db::Collection {
+    let mut collection = core::db::Collection::new(core::db::Source {
+        crate_name: crate_name(rust_path),
+        path: rust_path.to_string(),
+        rev: rev.map(|r| r.to_string()),
+        commit: rev.map(|r| git::commit(rust_path, r)),
+        prefix: None,
+        repo_path: git::repo_path(rust_path),
+    });
+    for (file, syntax) in source_files(rust_path, rev) {
+        let id_prefix = file.strip_suffix(".rs").unwrap();
+        collection.add_items(syntax.items, id_prefix, &file);
+    }
+    collection.resolve();
+    for diagnostic in collection.diagnostics() {
+        eprintln!("warning: {}", diagnostic);
+    }
+    collection
+}
+
+/// Parse the Rust files of the folder (or the file), from the working tree or a git revision.
+/// The paths are relative to the folder.
+fn source_files(rust_path: &str, rev: Option<&str>) -> Vec<(String, syn::File)> {
+    if let Some(rev) = rev {
+        git::find_rust_files(rust_path, rev)
+            .into_iter()
+            .map(|file| {
+                let syntax = git::parse_file(rust_path, rev, &file);
+                (file.to_str().unwrap().to_string(), syntax)
+            })
+            .collect()
     } else {
-        println!("{}", collection.parse_to_csv(only_json, no_header));
+        fs::find_rust_files(rust_path)
+            .into_iter()
+            .map(|file| {
+                let syntax = fs::parse_file(file.clone());
+                let file = if rust_path.ends_with(".rs") {
+                    file.file_name().unwrap().to_str()
+                } else {
+                    file.strip_prefix(rust_path).unwrap().to_str()
+                }
+                .unwrap();
+                (file.to_string(), syntax)
+            })
+            .collect()
+    }
+}
+
+/// Test code of a source folder: the `#[cfg(test)]` modules and `#[test]` functions of the source files, the files of
+/// their out-of-line test modules and the files of the `tests/` folder next to `src`.
+fn test_files(rust_path: &str, rev: Option<&str>) -> Vec<TestFile> {
+    let sources = source_files(rust_path, rev);
+    let modules = sources
+        .iter()
+        .flat_map(|(file, syntax)| core::coverage::test_modules(syntax, file))
+        .collect::<BTreeSet<String>>();
+    let mut files = sources
+        .into_iter()
+        .map(|(file, syntax)| TestFile {
+            module: Some(file.strip_suffix(".rs").unwrap().to_string()),
+            whole: modules.contains(&file) || file.starts_with("tests/"),
+            path: file,
+            syntax,
+        })
+        .collect::<Vec<TestFile>>();
+    let path = Path::new(rust_path);
+    if path.file_name().is_some_and(|name| name == "src") {
+        let tests = path.with_file_name("tests");
+        for (file, syntax) in source_files(tests.to_str().unwrap(), rev) {
+            files.push(TestFile {
+                path: format!("tests/{}", file.trim_start_matches('/')),
+                syntax,
+                module: None,
+                whole: true,
+            });
+        }
     }
+    files
 }